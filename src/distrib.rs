@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::prelude::*;
 
 fn is_rhel() -> bool {
     let file = fs::File::open("/etc/redhat-release");
@@ -47,3 +48,42 @@ pub fn get_package_manager() -> String {
         _ => "unknow".to_string()
     }
 }
+
+fn is_systemd() -> bool {
+    let mut comm = String::new();
+    match fs::File::open("/proc/1/comm").and_then(|mut f| f.read_to_string(&mut comm)) {
+        Ok(_) => comm.trim() == "systemd",
+        Err(_) => false
+    }
+}
+
+fn is_openrc() -> bool {
+    let file = fs::File::open("/sbin/openrc-run");
+    match file {
+        Ok(_) => true,
+        Err(_) => false
+    }
+}
+
+fn is_sysv() -> bool {
+    let file = fs::File::open("/etc/init.d");
+    match file {
+        Ok(_) => true,
+        Err(_) => false
+    }
+}
+
+pub fn get_init_system() -> String {
+    if is_systemd() {
+        "systemd".to_string()
+    }
+    else if is_openrc() {
+        "openrc".to_string()
+    }
+    else if is_sysv() {
+        "sysv".to_string()
+    }
+    else {
+        "unknow".to_string()
+    }
+}
@@ -1,5 +1,11 @@
 #[macro_use]
 extern crate clap;
+extern crate libc;
+extern crate regex;
+extern crate openssl;
+extern crate rustc_serialize;
+extern crate toml;
+extern crate yaml_rust;
 use clap::App;
 pub mod modules;
 pub mod util;
@@ -7,6 +13,30 @@ pub mod distrib;
 use modules::user;
 use modules::group;
 use modules::package;
+use modules::audit;
+use modules::mount;
+use modules::filesystem_usage;
+use modules::kernel_param;
+use modules::kernel_module;
+use modules::process;
+use modules::port;
+use modules::service;
+use modules::interface;
+use modules::route;
+use modules::dns;
+use modules::command;
+use modules::http;
+use modules::reachable;
+use modules::certificate;
+use modules::sshd_config;
+use modules::sudoers;
+use modules::cron;
+use modules::systemd_unit;
+use modules::pam;
+use modules::limits;
+use modules::login_defs;
+use modules::config;
+use modules::structured_file;
 
 fn main() {
 
@@ -88,5 +118,736 @@ fn main() {
             }
         }
     }
-}
 
+    // audit subcommand
+    if let Some(matches) = matches.subcommand_matches("audit") {
+        if let Some(matches) = matches.subcommand_matches("filesystem") {
+            let mounts = matches.value_of("mounts");
+            let allowed_suid = matches.value_of("allowed-suid");
+            let allowed_writable = matches.value_of("allowed-world-writable");
+            let test_result = audit::check_filesystem(mounts, allowed_suid, allowed_writable);
+            match test_result {
+                Ok(result) => {
+                    if result.error == 0 {
+                        println!("All tests success : {:?}", result);
+                        std::process::exit(0);
+                    }
+                    else {
+                        println!("Error during tests : {:?}", result);
+                        std::process::exit(1);
+                    }
+                }
+                Err(error) => {
+                    println!("System error : {:?}", error);
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+
+    // mount subcommand
+    if let Some(matches) = matches.subcommand_matches("mount") {
+        let path = matches.value_of("path").unwrap();
+        let exists = matches.value_of("exists").unwrap();
+        let source = matches.value_of("source");
+        let fstype = matches.value_of("fstype");
+        let options = matches.value_of("options");
+        let forbidden_options = matches.value_of("forbidden-options");
+        let fstab = matches.value_of("fstab");
+        let test_result = mount::check(path, exists, source, fstype, options, forbidden_options, fstab);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // filesystem_usage subcommand
+    if let Some(matches) = matches.subcommand_matches("filesystem_usage") {
+        let path = matches.value_of("path").unwrap();
+        let min_free = matches.value_of("min-free");
+        let min_free_inodes = matches.value_of("min-free-inodes");
+        let test_result = filesystem_usage::check(path, min_free, min_free_inodes);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // kernel_param subcommand
+    if let Some(matches) = matches.subcommand_matches("kernel_param") {
+        let name = matches.value_of("name").unwrap();
+        let value = matches.value_of("value");
+        let persisted = matches.value_of("persisted");
+        let test_result = kernel_param::check(name, value, persisted);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // kernel_module subcommand
+    if let Some(matches) = matches.subcommand_matches("kernel_module") {
+        let name = matches.value_of("name").unwrap();
+        let loaded = matches.value_of("loaded");
+        let blacklisted = matches.value_of("blacklisted");
+        let disabled = matches.value_of("disabled");
+        let test_result = kernel_module::check(name, loaded, blacklisted, disabled);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // process subcommand
+    if let Some(matches) = matches.subcommand_matches("process") {
+        let name = matches.value_of("name");
+        let cmdline = matches.value_of("cmdline");
+        let count = matches.value_of("count");
+        let min_count = matches.value_of("min-count");
+        let max_count = matches.value_of("max-count");
+        let user = matches.value_of("user");
+        let group = matches.value_of("group");
+        let parent = matches.value_of("parent");
+        let zombie = matches.value_of("zombie");
+        let soft_limits: Vec<&str> = matches.values_of("soft-limit").map(|v| v.collect()).unwrap_or(Vec::new());
+        let hard_limits: Vec<&str> = matches.values_of("hard-limit").map(|v| v.collect()).unwrap_or(Vec::new());
+        let env: Vec<&str> = matches.values_of("env").map(|v| v.collect()).unwrap_or(Vec::new());
+        let cwd = matches.value_of("cwd");
+        let capabilities = matches.value_of("capabilities");
+        let test_result = process::check(&process::Options {
+            name: name,
+            cmdline: cmdline,
+            count: count,
+            min_count: min_count,
+            max_count: max_count,
+            user: user,
+            group: group,
+            parent: parent,
+            zombie: zombie,
+            soft_limits: &soft_limits,
+            hard_limits: &hard_limits,
+            env: &env,
+            cwd: cwd,
+            capabilities: capabilities
+        });
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // port subcommand
+    if let Some(matches) = matches.subcommand_matches("port") {
+        let protocol = matches.value_of("protocol");
+        let port = matches.value_of("port");
+        let path = matches.value_of("path");
+        let listening = matches.value_of("listening").unwrap();
+        let address = matches.value_of("address");
+        let process = matches.value_of("process");
+        let test_result = port::check(protocol, port, path, listening, address, process);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // service subcommand
+    if let Some(matches) = matches.subcommand_matches("service") {
+        let name = matches.value_of("name").unwrap();
+        let enabled = matches.value_of("enabled");
+        let active = matches.value_of("active");
+        let running = matches.value_of("running");
+        let masked = matches.value_of("masked");
+        let root = matches.value_of("root");
+        let init = matches.value_of("init");
+        let test_result = service::check(name, enabled, active, running, masked, root, init);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // interface subcommand
+    if let Some(matches) = matches.subcommand_matches("interface") {
+        let name = matches.value_of("name").unwrap();
+        let exists = matches.value_of("exists").unwrap();
+        let state = matches.value_of("state");
+        let mtu = matches.value_of("mtu");
+        let mac = matches.value_of("mac");
+        let addresses = matches.value_of("addresses");
+        let contains_addresses = matches.value_of("contains-addresses");
+        let test_result = interface::check(name, exists, state, mtu, mac, addresses, contains_addresses);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // route subcommand
+    if let Some(matches) = matches.subcommand_matches("route") {
+        let destination = matches.value_of("destination").unwrap();
+        let exists = matches.value_of("exists").unwrap();
+        let gateway = matches.value_of("gateway");
+        let interface = matches.value_of("interface");
+        let test_result = route::check(destination, exists, gateway, interface);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // dns subcommand
+    if let Some(matches) = matches.subcommand_matches("dns") {
+        if let Some(matches) = matches.subcommand_matches("resolve") {
+            let name = matches.value_of("name").unwrap();
+            let resolvable = matches.value_of("resolvable");
+            let addresses = matches.value_of("addresses");
+            let test_result = dns::check_resolve(name, resolvable, addresses);
+            match test_result {
+                Ok(result) => {
+                    if result.error == 0 {
+                        println!("All tests success : {:?}", result);
+                        std::process::exit(0);
+                    }
+                    else {
+                        println!("Error during tests : {:?}", result);
+                        std::process::exit(1);
+                    }
+                }
+                Err(error) => {
+                    println!("System error : {:?}", error);
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(matches) = matches.subcommand_matches("hosts") {
+            let name = matches.value_of("name").unwrap();
+            let exists = matches.value_of("exists");
+            let addresses = matches.value_of("addresses");
+            let test_result = dns::check_hosts(name, exists, addresses);
+            match test_result {
+                Ok(result) => {
+                    if result.error == 0 {
+                        println!("All tests success : {:?}", result);
+                        std::process::exit(0);
+                    }
+                    else {
+                        println!("Error during tests : {:?}", result);
+                        std::process::exit(1);
+                    }
+                }
+                Err(error) => {
+                    println!("System error : {:?}", error);
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(matches) = matches.subcommand_matches("resolv_conf") {
+            let nameservers = matches.value_of("nameservers");
+            let search = matches.value_of("search");
+            let options = matches.value_of("options");
+            let test_result = dns::check_resolv_conf(nameservers, search, options);
+            match test_result {
+                Ok(result) => {
+                    if result.error == 0 {
+                        println!("All tests success : {:?}", result);
+                        std::process::exit(0);
+                    }
+                    else {
+                        println!("Error during tests : {:?}", result);
+                        std::process::exit(1);
+                    }
+                }
+                Err(error) => {
+                    println!("System error : {:?}", error);
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+
+    // command subcommand
+    if let Some(matches) = matches.subcommand_matches("command") {
+        let command_line = matches.value_of("command").unwrap();
+        let shell = matches.value_of("shell");
+        let username = matches.value_of("user");
+        let env = matches.value_of("env");
+        let timeout = matches.value_of("timeout");
+        let exit_status = matches.value_of("exit-status");
+        let stdout = matches.value_of("stdout");
+        let stdout_contains = matches.value_of("stdout-contains");
+        let stdout_regex = matches.value_of("stdout-regex");
+        let stderr = matches.value_of("stderr");
+        let stderr_contains = matches.value_of("stderr-contains");
+        let stderr_regex = matches.value_of("stderr-regex");
+        let max_runtime = matches.value_of("max-runtime");
+        let test_result = command::check(command_line, shell, username, env, timeout, exit_status, stdout, stdout_contains,
+                                         stdout_regex, stderr, stderr_contains, stderr_regex, max_runtime);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // http subcommand
+    if let Some(matches) = matches.subcommand_matches("http") {
+        let url = matches.value_of("url").unwrap();
+        let method = matches.value_of("method");
+        let request_headers: Vec<&str> = matches.values_of("request-header").map(|v| v.collect()).unwrap_or(Vec::new());
+        let timeout = matches.value_of("timeout");
+        let retries = matches.value_of("retries");
+        let retry_delay = matches.value_of("retry-delay");
+        let max_redirects = matches.value_of("max-redirects");
+        let ca_file = matches.value_of("ca-file");
+        let tls_verify = matches.value_of("tls-verify");
+        let status = matches.value_of("status");
+        let max_time = matches.value_of("max-time");
+        let headers: Vec<&str> = matches.values_of("header").map(|v| v.collect()).unwrap_or(Vec::new());
+        let body_contains = matches.value_of("body-contains");
+        let body_regex = matches.value_of("body-regex");
+        let json: Vec<&str> = matches.values_of("json").map(|v| v.collect()).unwrap_or(Vec::new());
+        let test_result = http::check(&http::Options {
+            url: url,
+            method: method,
+            request_headers: &request_headers,
+            timeout: timeout,
+            retries: retries,
+            retry_delay: retry_delay,
+            max_redirects: max_redirects,
+            ca_file: ca_file,
+            tls_verify: tls_verify,
+            status: status,
+            max_time: max_time,
+            headers: &headers,
+            body_contains: body_contains,
+            body_regex: body_regex,
+            json: &json
+        });
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // reachable subcommand
+    if let Some(matches) = matches.subcommand_matches("reachable") {
+        let host = matches.value_of("host").unwrap();
+        let port = matches.value_of("port").unwrap();
+        let reachable = matches.value_of("reachable").unwrap();
+        let protocol = matches.value_of("protocol");
+        let timeout = matches.value_of("timeout");
+        let payload = matches.value_of("payload");
+        let expect = matches.value_of("expect");
+        let test_result = reachable::check(host, port, reachable, protocol, timeout, payload, expect);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // certificate subcommand
+    if let Some(matches) = matches.subcommand_matches("certificate") {
+        let path = matches.value_of("path").unwrap();
+        let index = matches.value_of("index");
+        let min_days = matches.value_of("min-days");
+        let subject = matches.value_of("subject");
+        let san = matches.value_of("san");
+        let issuer = matches.value_of("issuer");
+        let key_type = matches.value_of("key-type");
+        let key_size = matches.value_of("key-size");
+        let private_key = matches.value_of("private-key");
+        let test_result = certificate::check(path, index, min_days, subject, san, issuer, key_type, key_size,
+                                             private_key);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // sshd_config subcommand
+    if let Some(matches) = matches.subcommand_matches("sshd_config") {
+        let settings: Vec<&str> = matches.values_of("setting").map(|v| v.collect()).unwrap_or(Vec::new());
+        let allowed: Vec<&str> = matches.values_of("allowed").map(|v| v.collect()).unwrap_or(Vec::new());
+        let match_block = matches.value_of("match");
+        let test_result = sshd_config::check(&settings, &allowed, match_block);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // sudoers subcommand
+    if let Some(matches) = matches.subcommand_matches("sudoers") {
+        let username = matches.value_of("user");
+        let command = matches.value_of("command");
+        let runas = matches.value_of("runas");
+        let can_run = matches.value_of("can-run");
+        let allowed_users = matches.value_of("allowed-users");
+        let nopasswd_users = matches.value_of("nopasswd-users");
+        let test_result = sudoers::check(username, command, runas, can_run, allowed_users, nopasswd_users);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // cron subcommand
+    if let Some(matches) = matches.subcommand_matches("cron") {
+        let command = matches.value_of("command");
+        let username = matches.value_of("user");
+        let schedule = matches.value_of("schedule");
+        let exists = matches.value_of("exists");
+        let allowed_users = matches.value_of("allowed-users");
+        let test_result = cron::check(command, username, schedule, exists, allowed_users);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // systemd_unit subcommand
+    if let Some(matches) = matches.subcommand_matches("systemd_unit") {
+        let name = matches.value_of("name").unwrap();
+        let directives: Vec<&str> = matches.values_of("directive").map(|v| v.collect()).unwrap_or(Vec::new());
+        let root = matches.value_of("root");
+        let test_result = systemd_unit::check(name, &directives, root);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // pam subcommand
+    if let Some(matches) = matches.subcommand_matches("pam") {
+        let pam_type = matches.value_of("type").unwrap();
+        let service = matches.value_of("service");
+        let module = matches.value_of("module").unwrap();
+        let exists = matches.value_of("exists");
+        let control = matches.value_of("control");
+        let args = matches.value_of("args");
+        let before = matches.value_of("before");
+        let after = matches.value_of("after");
+        let test_result = pam::check(pam_type, service, module, exists, control, args, before, after);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // limits subcommand
+    if let Some(matches) = matches.subcommand_matches("limits") {
+        let username = matches.value_of("user").unwrap();
+        let soft: Vec<&str> = matches.values_of("soft").map(|v| v.collect()).unwrap_or(Vec::new());
+        let hard: Vec<&str> = matches.values_of("hard").map(|v| v.collect()).unwrap_or(Vec::new());
+        let test_result = limits::check(username, &soft, &hard);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // login_defs subcommand
+    if let Some(matches) = matches.subcommand_matches("login_defs") {
+        let settings: Vec<&str> = matches.values_of("setting").map(|v| v.collect()).unwrap_or(Vec::new());
+        let test_result = login_defs::check(&settings);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // config subcommand
+    if let Some(matches) = matches.subcommand_matches("config") {
+        let path = matches.value_of("path").unwrap();
+        let format = matches.value_of("format");
+        let section = matches.value_of("section");
+        let key = matches.value_of("key").unwrap();
+        let exists = matches.value_of("exists");
+        let value = matches.value_of("value");
+        let regex = matches.value_of("regex");
+        let test_result = config::check(path, format, section, key, exists, value, regex);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // structured_file subcommand
+    if let Some(matches) = matches.subcommand_matches("structured_file") {
+        let path = matches.value_of("path").unwrap();
+        let format = matches.value_of("format");
+        let query = matches.value_of("query").unwrap();
+        let exists = matches.value_of("exists");
+        let value = matches.value_of("value");
+        let regex = matches.value_of("regex");
+        let test_result = structured_file::check(path, format, query, exists, value, regex);
+        match test_result {
+            Ok(result) => {
+                if result.error == 0 {
+                    println!("All tests success : {:?}", result);
+                    std::process::exit(0);
+                }
+                else {
+                    println!("Error during tests : {:?}", result);
+                    std::process::exit(1);
+                }
+            }
+            Err(error) => {
+                println!("System error : {:?}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use modules::test;
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+const S_IWOTH: u32 = 0o0002;
+
+#[derive(Debug)]
+pub enum AuditError {
+    Io(io::Error)
+}
+
+impl From<io::Error> for AuditError {
+    fn from(err: io::Error) -> AuditError {
+        AuditError::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FindingKind {
+    Suid,
+    WorldWritable
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub path: String,
+    pub mode: u32,
+    pub kind: FindingKind
+}
+
+/// returns the finding for a path, if its metadata makes it one
+fn get_finding(path: &Path, metadata: &fs::Metadata) -> Option<Finding> {
+    let mode = metadata.mode();
+    let kind = if metadata.is_file() && mode & (S_ISUID | S_ISGID) != 0 {
+        FindingKind::Suid
+    }
+    else if metadata.is_dir() && mode & S_IWOTH != 0 && mode & S_ISVTX == 0 {
+        FindingKind::WorldWritable
+    }
+    else {
+        return None;
+    };
+    Some(Finding {
+        path: path.to_string_lossy().into_owned(),
+        mode: mode & 0o7777,
+        kind: kind
+    })
+}
+
+/// walks a directory without crossing to other filesystems (device `dev`)
+/// directories which can't be read (permissions, vanished...) are skipped
+fn walk(path: &Path, dev: u64, findings: &mut Vec<Finding>) {
+    let entries = match fs::read_dir(path) {
+        Ok(e) => e,
+        Err(_) => return
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => continue
+        };
+        let entry_path = entry.path();
+        let metadata = match fs::symlink_metadata(&entry_path) {
+            Ok(m) => m,
+            Err(_) => continue
+        };
+        if metadata.dev() != dev {
+            continue;
+        }
+        if let Some(finding) = get_finding(&entry_path, &metadata) {
+            findings.push(finding);
+        }
+        if metadata.is_dir() {
+            walk(&entry_path, dev, findings);
+        }
+    }
+}
+
+/// returns the SUID/SGID files and world-writable directories without the
+/// sticky bit under a mount point, staying on the mount point filesystem
+pub fn get_findings(mount_point: &str) -> Result<Vec<Finding>, io::Error> {
+    let path = Path::new(mount_point);
+    let metadata = try!(fs::symlink_metadata(path));
+    let mut findings = Vec::new();
+    if let Some(finding) = get_finding(path, &metadata) {
+        findings.push(finding);
+    }
+    walk(path, metadata.dev(), &mut findings);
+    Ok(findings)
+}
+
+fn check_finding(finding: &Finding, allowed_suid: &Vec<&str>, allowed_writable: &Vec<&str>, result: &mut test::TestResult) {
+    let (test_name, allowed, message) = match finding.kind {
+        FindingKind::Suid => ("audit - suid", allowed_suid, "unexpected SUID/SGID file"),
+        FindingKind::WorldWritable => ("audit - world writable", allowed_writable, "unexpected world-writable directory without sticky bit")
+    };
+    let condition = allowed.contains(&finding.path.as_ref());
+    let expected = format!("{} in allow-list", finding.path);
+    let actual = format!("{} has mode {:04o}", finding.path, finding.mode);
+    test::update_test_result(condition, test_name, &expected, &actual, message, result);
+}
+
+fn split_list(list: Option<&str>) -> Vec<&str> {
+    match list {
+        None => Vec::new(),
+        Some(l) => l.split(',').filter(|s| !s.is_empty()).collect()
+    }
+}
+
+pub fn check_filesystem(mounts: Option<&str>,
+                        allowed_suid: Option<&str>,
+                        allowed_writable: Option<&str>) -> Result<test::TestResult, AuditError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let mut mount_list = split_list(mounts);
+    if mount_list.is_empty() {
+        mount_list.push("/");
+    }
+    let allowed_suid = split_list(allowed_suid);
+    let allowed_writable = split_list(allowed_writable);
+    for mount_point in mount_list {
+        let findings = try!(get_findings(mount_point));
+        for finding in findings {
+            check_finding(&finding, &allowed_suid, &allowed_writable, &mut result);
+        }
+    }
+    Ok(result)
+}
+
+// TESTS
+
+#[cfg(test)]
+fn create_audit_tree(name: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let root = format!("{}/lothaire-audit-{}", ::std::env::temp_dir().display(), name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(format!("{}/bin", root)).unwrap();
+    fs::create_dir_all(format!("{}/tmp", root)).unwrap();
+    fs::create_dir_all(format!("{}/share", root)).unwrap();
+    fs::File::create(format!("{}/bin/su", root)).unwrap();
+    fs::File::create(format!("{}/bin/ls", root)).unwrap();
+    fs::set_permissions(format!("{}/bin/su", root), fs::Permissions::from_mode(0o4755)).unwrap();
+    fs::set_permissions(format!("{}/bin/ls", root), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::set_permissions(format!("{}/tmp", root), fs::Permissions::from_mode(0o1777)).unwrap();
+    fs::set_permissions(format!("{}/share", root), fs::Permissions::from_mode(0o777)).unwrap();
+    root
+}
+
+#[test]
+fn get_findings_test_success() {
+    let root = create_audit_tree("findings");
+    let findings = get_findings(&root).unwrap();
+    assert_eq!(findings.len(), 2);
+    let suid: Vec<&Finding> = findings.iter().filter(|f| f.kind == FindingKind::Suid).collect();
+    assert_eq!(suid.len(), 1);
+    assert_eq!(suid[0].path, format!("{}/bin/su", root));
+    assert_eq!(suid[0].mode, 0o4755);
+    let writable: Vec<&Finding> = findings.iter().filter(|f| f.kind == FindingKind::WorldWritable).collect();
+    assert_eq!(writable.len(), 1);
+    assert_eq!(writable[0].path, format!("{}/share", root));
+    assert!(get_findings("/notexists").is_err());
+}
+
+#[test]
+fn check_filesystem_test_success() {
+    let root = create_audit_tree("check");
+    let su = format!("{}/bin/su", root);
+    let share = format!("{}/share", root);
+    let result = check_filesystem(Some(&root), Some(&su), Some(&share)).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check_filesystem(Some(&root), Some(&su), None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+    for summary in &result.summary {
+        if let &test::UnitResult::Error(ref e) = summary {
+            assert_eq!(e.test, "audit - world writable");
+            assert_eq!(e.expected, format!("{} in allow-list", share));
+            assert_eq!(e.actual, format!("{} has mode 0777", share));
+        }
+    }
+}
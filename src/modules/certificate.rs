@@ -0,0 +1,337 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::num;
+use openssl::asn1::Asn1Time;
+use openssl::error::ErrorStack;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey};
+use openssl::x509::{X509, X509NameRef, X509Ref};
+use modules::test;
+use util;
+
+#[derive(Debug)]
+pub enum CertificateError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    Ssl(ErrorStack),
+    NoCertificate(String),
+    Index(String)
+}
+
+impl From<io::Error> for CertificateError {
+    fn from(err: io::Error) -> CertificateError {
+        CertificateError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for CertificateError {
+    fn from(err: num::ParseIntError) -> CertificateError {
+        CertificateError::ParseInt(err)
+    }
+}
+
+impl From<ErrorStack> for CertificateError {
+    fn from(err: ErrorStack) -> CertificateError {
+        CertificateError::Ssl(err)
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, io::Error> {
+    let mut file = try!(fs::File::open(path));
+    let mut content = Vec::new();
+    try!(file.read_to_end(&mut content));
+    Ok(content)
+}
+
+/// reads every certificate of a PEM bundle, or a single DER certificate
+pub fn get_certificates(path: &str) -> Result<Vec<X509>, CertificateError> {
+    let content = try!(read_file(path));
+    let certificates = if content.windows(10).any(|w| w == b"-----BEGIN") {
+        try!(X509::stack_from_pem(&content))
+    }
+    else {
+        vec![try!(X509::from_der(&content))]
+    };
+    if certificates.is_empty() {
+        return Err(CertificateError::NoCertificate(path.to_string()));
+    }
+    Ok(certificates)
+}
+
+/// the common name, or the whole name when there is no CN
+pub fn get_cn(name: &X509NameRef) -> String {
+    let cn = name.entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|e| e.data().to_string().ok());
+    match cn {
+        Some(cn) => cn,
+        None => name.entries()
+            .filter_map(|e| e.data().to_string().ok())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+/// DNS names and IP addresses of the subjectAltName extension
+pub fn get_sans(certificate: &X509Ref) -> Vec<String> {
+    let mut sans = Vec::new();
+    if let Some(names) = certificate.subject_alt_names() {
+        for name in names.iter() {
+            if let Some(dns) = name.dnsname() {
+                sans.push(dns.to_string());
+            }
+            else if let Some(ip) = name.ipaddress() {
+                if ip.len() == 4 {
+                    sans.push(IpAddr::V4(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3])).to_string());
+                }
+                else if ip.len() == 16 {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(ip);
+                    sans.push(IpAddr::V6(Ipv6Addr::from(octets)).to_string());
+                }
+            }
+        }
+    }
+    sans
+}
+
+/// days left before not_after, negative once expired
+pub fn get_days_left(certificate: &X509Ref) -> Result<i32, ErrorStack> {
+    let now = try!(Asn1Time::days_from_now(0));
+    let diff = try!(now.diff(certificate.not_after()));
+    Ok(diff.days as i32)
+}
+
+pub fn get_key_type(certificate: &X509Ref) -> Result<(String, u32), ErrorStack> {
+    let key = try!(certificate.public_key());
+    let key_type = match key.id() {
+        Id::RSA => "rsa",
+        Id::DSA => "dsa",
+        Id::EC => "ec",
+        Id::ED25519 => "ed25519",
+        Id::ED448 => "ed448",
+        _ => "unknown"
+    };
+    Ok((key_type.to_string(), key.bits()))
+}
+
+fn check_expiry(min_days: i32, certificate: &X509Ref, result: &mut test::TestResult) -> Result<(), ErrorStack> {
+    let test_name = "certificate - expiry";
+    let cn = get_cn(certificate.subject_name());
+    let days_left = try!(get_days_left(certificate));
+    let condition = days_left >= min_days;
+    test::update_test_result(condition, test_name, &format!("{}: valid for {} days", cn, min_days),
+                             &format!("{}: valid for {} days (not after {})", cn, days_left, certificate.not_after()),
+                             "certificate expires too soon", result);
+    Ok(())
+}
+
+fn check_subject(subject: &str, certificate: &X509Ref, result: &mut test::TestResult) {
+    let test_name = "certificate - subject";
+    let cn = get_cn(certificate.subject_name());
+    test::update_test_result(cn == subject, test_name, subject, &cn, "incorrect subject", result);
+}
+
+fn check_issuer(issuer: &str, certificate: &X509Ref, result: &mut test::TestResult) {
+    let test_name = "certificate - issuer";
+    let cn = get_cn(certificate.subject_name());
+    let issuer_cn = get_cn(certificate.issuer_name());
+    test::update_test_result(issuer_cn == issuer, test_name, &format!("{}: {}", cn, issuer),
+                             &format!("{}: {}", cn, issuer_cn), "incorrect issuer", result);
+}
+
+fn check_sans(expected: &str, certificate: &X509Ref, result: &mut test::TestResult) {
+    let test_name = "certificate - san";
+    let cn = get_cn(certificate.subject_name());
+    let sans = get_sans(certificate);
+    let condition = expected.split(',').filter(|s| !s.is_empty()).all(|e| sans.iter().any(|s| s == e));
+    test::update_test_result(condition, test_name, &format!("{}: {}", cn, expected),
+                             &format!("{}: {}", cn, sans.join(",")), "missing subject alternative names", result);
+}
+
+fn check_key(key_type: Option<&str>,
+             key_size: Option<i32>,
+             certificate: &X509Ref,
+             result: &mut test::TestResult) -> Result<(), ErrorStack> {
+    let cn = get_cn(certificate.subject_name());
+    let (actual_type, actual_size) = try!(get_key_type(certificate));
+    key_type.map(|t| {
+        let condition = actual_type == t.to_lowercase();
+        test::update_test_result(condition, "certificate - key type", &format!("{}: {}", cn, t),
+                                 &format!("{}: {}", cn, actual_type), "incorrect key type", result);
+    });
+    key_size.map(|s| {
+        let condition = actual_size as i32 == s;
+        test::update_test_result(condition, "certificate - key size", &format!("{}: {}", cn, s),
+                                 &format!("{}: {}", cn, actual_size), "incorrect key size", result);
+    });
+    Ok(())
+}
+
+fn check_private_key(path: &str, certificate: &X509Ref, result: &mut test::TestResult) -> Result<(), CertificateError> {
+    let test_name = "certificate - key match";
+    let cn = get_cn(certificate.subject_name());
+    let private_key = try!(PKey::private_key_from_pem(&try!(read_file(path))));
+    let condition = try!(certificate.public_key()).public_eq(&private_key);
+    let actual = if condition { format!("{}: {}", cn, path) } else { format!("{}: {} doesn't match", cn, path) };
+    test::update_test_result(condition, test_name, &format!("{}: {}", cn, path), &actual,
+                             "private key doesn't match the certificate", result);
+    Ok(())
+}
+
+/// the certificates of the file the other tests than expiry apply to:
+/// `0` (default) for the first one, the leaf of a chain, `N` for the Nth one or `all`
+pub fn select_certificates<'a>(certificates: &'a [X509],
+                               index: Option<&str>) -> Result<Vec<&'a X509>, CertificateError> {
+    match index.unwrap_or("0") {
+        "all" => Ok(certificates.iter().collect()),
+        i => match try!(i.parse::<usize>()) {
+            i if i < certificates.len() => Ok(vec![&certificates[i]]),
+            _ => Err(CertificateError::Index(i.to_string()))
+        }
+    }
+}
+
+/// the expiry is tested for every certificate of the file, the other tests for the ones selected by `index`.
+/// Each certificate gets its own results, labelled by its common name.
+pub fn check(path: &str,
+             index: Option<&str>,
+             min_days: Option<&str>,
+             subject: Option<&str>,
+             sans: Option<&str>,
+             issuer: Option<&str>,
+             key_type: Option<&str>,
+             key_size: Option<&str>,
+             private_key: Option<&str>) -> Result<test::TestResult, CertificateError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let min_days = try!(util::parse_int(min_days));
+    let key_size = try!(util::parse_int(key_size));
+    let certificates = try!(get_certificates(path));
+    let selected = try!(select_certificates(&certificates, index));
+    if let Some(min_days) = min_days {
+        for certificate in &certificates {
+            try!(check_expiry(min_days, certificate, &mut result));
+        }
+    }
+    for certificate in selected {
+        subject.map(|s| {
+            check_subject(s, certificate, &mut result);
+        });
+        sans.map(|s| {
+            check_sans(s, certificate, &mut result);
+        });
+        issuer.map(|i| {
+            check_issuer(i, certificate, &mut result);
+        });
+        if key_type.is_some() || key_size.is_some() {
+            try!(check_key(key_type, key_size, certificate, &mut result));
+        }
+        if let Some(private_key) = private_key {
+            try!(check_private_key(private_key, certificate, &mut result));
+        }
+    }
+    Ok(result)
+}
+
+// TESTS
+
+#[cfg(test)]
+const FIXTURES: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/certificate");
+
+#[cfg(test)]
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+#[test]
+fn get_certificates_test_success() {
+    let certificates = get_certificates(&fixture("chain.pem")).unwrap();
+    assert_eq!(certificates.len(), 2);
+    assert_eq!(get_cn(certificates[0].subject_name()), "www.example.com");
+    assert_eq!(get_cn(certificates[1].subject_name()), "Lothaire Test CA");
+    let certificates = get_certificates(&fixture("server.der")).unwrap();
+    assert_eq!(certificates.len(), 1);
+    assert_eq!(get_cn(certificates[0].issuer_name()), "Lothaire Test CA");
+    assert!(get_certificates(&fixture("server.key")).is_err());
+    assert!(get_certificates(&fixture("notexists.pem")).is_err());
+}
+
+#[test]
+fn get_sans_test_success() {
+    let certificates = get_certificates(&fixture("server.pem")).unwrap();
+    assert_eq!(get_sans(&certificates[0]), vec!["www.example.com", "example.com", "10.0.0.1"]);
+    let certificates = get_certificates(&fixture("ca.pem")).unwrap();
+    assert!(get_sans(&certificates[0]).is_empty());
+}
+
+#[test]
+fn get_days_left_test_success() {
+    let certificates = get_certificates(&fixture("server.pem")).unwrap();
+    assert!(get_days_left(&certificates[0]).unwrap() > 3650);
+    let certificates = get_certificates(&fixture("expired.pem")).unwrap();
+    assert!(get_days_left(&certificates[0]).unwrap() < 0);
+}
+
+#[test]
+fn get_key_type_test_success() {
+    let certificates = get_certificates(&fixture("server.pem")).unwrap();
+    assert_eq!(get_key_type(&certificates[0]).unwrap(), ("rsa".to_string(), 2048));
+    let certificates = get_certificates(&fixture("ec.pem")).unwrap();
+    assert_eq!(get_key_type(&certificates[0]).unwrap(), ("ec".to_string(), 256));
+}
+
+#[test]
+fn check_test_success() {
+    let result = check(&fixture("chain.pem"), None, Some("30"), Some("www.example.com"), Some("example.com,10.0.0.1"),
+                       Some("Lothaire Test CA"), Some("RSA"), Some("2048"), Some(&fixture("server.key"))).unwrap();
+    assert_eq!(result.success, 8);
+    assert_eq!(result.error, 0);
+    let result = check(&fixture("ec.pem"), None, None, None, None, None, Some("ec"), None,
+                       Some(&fixture("ec.key"))).unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_test_error() {
+    let result = check(&fixture("expired.pem"), None, Some("30"), Some("www.example.com"), Some("example.com"),
+                       Some("Other CA"), Some("ec"), Some("4096"), Some(&fixture("other.key"))).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 7);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "expired.example.com: valid for 30 days");
+            assert!(e.actual.contains("not after Jan  1 00:00:00 2001 GMT"));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check(&fixture("server.pem"), None, Some("soon"), None, None, None, None, None, None).is_err());
+    assert!(check(&fixture("server.pem"), None, None, None, None, None, None, None, Some(&fixture("ca.pem"))).is_err());
+    assert!(check(&fixture("chain.pem"), Some("2"), None, Some("www.example.com"), None, None, None, None,
+                  None).is_err());
+    assert!(check(&fixture("chain.pem"), Some("last"), None, None, None, None, None, None, None).is_err());
+}
+
+#[test]
+fn check_test_index() {
+    let result = check(&fixture("chain.pem"), Some("1"), None, Some("Lothaire Test CA"), None, Some("Lothaire Test CA"),
+                       None, None, None).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check(&fixture("chain.pem"), Some("all"), None, None, None, Some("Lothaire Test CA"), Some("rsa"),
+                       None, None).unwrap();
+    assert_eq!(result.success, 4);
+    match result.summary[2] {
+        test::UnitResult::Success(ref s) => assert_eq!(s.expected, "Lothaire Test CA: Lothaire Test CA"),
+        test::UnitResult::Error(_) => panic!("error in test")
+    }
+    let result = check(&fixture("chain.pem"), Some("all"), None, Some("www.example.com"), None, None, None, None,
+                       None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+}
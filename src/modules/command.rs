@@ -0,0 +1,421 @@
+use std::cmp;
+use std::ffi::CString;
+use std::io;
+use std::io::prelude::*;
+use std::num;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::str;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use libc;
+use regex::{self, Regex};
+use modules::test;
+use modules::user;
+use util;
+
+#[derive(Debug)]
+pub enum CommandError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    Duration(util::DurationError),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error),
+    User(user::UserError),
+    Env(String)
+}
+
+impl From<io::Error> for CommandError {
+    fn from(err: io::Error) -> CommandError {
+        CommandError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for CommandError {
+    fn from(err: num::ParseIntError) -> CommandError {
+        CommandError::ParseInt(err)
+    }
+}
+
+impl From<util::DurationError> for CommandError {
+    fn from(err: util::DurationError) -> CommandError {
+        CommandError::Duration(err)
+    }
+}
+
+impl From<str::ParseBoolError> for CommandError {
+    fn from(err: str::ParseBoolError) -> CommandError {
+        CommandError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for CommandError {
+    fn from(err: regex::Error) -> CommandError {
+        CommandError::Regex(err)
+    }
+}
+
+impl From<user::UserError> for CommandError {
+    fn from(err: user::UserError) -> CommandError {
+        CommandError::User(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Execution {
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub runtime: Duration,
+    pub timed_out: bool
+}
+
+/// without a shell, the command line is split on whitespace (no quoting)
+pub fn build_command(command_line: &str, shell: bool) -> Result<Command, io::Error> {
+    if shell {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(command_line);
+        return Ok(command);
+    }
+    let mut words = command_line.split_whitespace();
+    match words.next() {
+        Some(program) => {
+            let mut command = Command::new(program);
+            command.args(words);
+            Ok(command)
+        }
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "empty command"))
+    }
+}
+
+/// parses `KEY=VALUE,KEY2=VALUE2`
+pub fn parse_env(env: &str) -> Result<Vec<(String, String)>, CommandError> {
+    let mut vars = Vec::new();
+    for entry in env.split(',').filter(|e| !e.is_empty()) {
+        match entry.find('=') {
+            Some(i) if i > 0 => vars.push((entry[..i].to_string(), entry[i + 1..].to_string())),
+            _ => return Err(CommandError::Env(entry.to_string()))
+        }
+    }
+    Ok(vars)
+}
+
+/// the supplementary groups of the user, like initgroups(3) but read before the fork
+pub fn get_group_list(name: &str, gid: libc::gid_t) -> Result<Vec<libc::gid_t>, io::Error> {
+    let c_name = try!(CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)));
+    let mut size: libc::c_int = 32;
+    loop {
+        let mut groups = vec![0; size as usize];
+        let mut count = size;
+        let ret = unsafe { libc::getgrouplist(c_name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) };
+        if ret >= 0 {
+            groups.truncate(count as usize);
+            return Ok(groups);
+        }
+        // glibc returns the number of groups needed in count
+        size = cmp::max(count, size * 2);
+    }
+}
+
+/// runs the command as the user, with its groups instead of the ones of lothaire
+fn set_user(command: &mut Command, user: &user::User) -> Result<(), io::Error> {
+    let uid = user.uid as libc::uid_t;
+    let gid = user.gid as libc::gid_t;
+    let groups = try!(get_group_list(&user.name, gid));
+    command.env("HOME", &user.home).env("USER", &user.name).env("LOGNAME", &user.name);
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setgroups(groups.len() as _, groups.as_ptr()) != 0 || libc::setgid(gid) != 0
+               || libc::setuid(uid) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// the output read so far, and a message when the pipe is closed
+struct Pipe {
+    content: Arc<Mutex<Vec<u8>>>,
+    closed: mpsc::Receiver<()>
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> Pipe {
+    let content = Arc::new(Mutex::new(Vec::new()));
+    let (sender, receiver) = mpsc::channel();
+    let shared = content.clone();
+    thread::spawn(move || {
+        if let Some(mut p) = pipe {
+            let mut buffer = [0; 4096];
+            loop {
+                match p.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => shared.lock().unwrap().extend_from_slice(&buffer[..n])
+                }
+            }
+        }
+        let _ = sender.send(());
+    });
+    Pipe {
+        content: content,
+        closed: receiver
+    }
+}
+
+/// a process started in the background by the command can keep the pipe open,
+/// so the output is the one read before the deadline
+fn collect(pipe: Pipe, deadline: Instant) -> String {
+    let _ = pipe.closed.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+    let content = pipe.content.lock().unwrap();
+    String::from_utf8_lossy(&content).into_owned()
+}
+
+/// kills the whole process group, so children of a shell don't keep the pipes open
+fn kill(child: &mut Child) -> Result<(), io::Error> {
+    unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+    try!(child.wait());
+    Ok(())
+}
+
+/// runs the command in its own process group, killing it when the timeout expires.
+/// The output is read until the timeout or the exit, with a short delay to read what was written before.
+pub fn run(command: &mut Command, timeout: Option<Duration>) -> Result<Execution, io::Error> {
+    let start = Instant::now();
+    let mut child = try!(command.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn());
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = try!(child.try_wait()) {
+            break status.code();
+        }
+        if timeout.map_or(false, |t| start.elapsed() >= t) {
+            try!(kill(&mut child));
+            timed_out = true;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let runtime = start.elapsed();
+    let deadline = cmp::max(timeout.map_or(start, |t| start + t), Instant::now()) + Duration::from_millis(100);
+    Ok(Execution {
+        status: status,
+        stdout: collect(stdout, deadline),
+        stderr: collect(stderr, deadline),
+        runtime: runtime,
+        timed_out: timed_out
+    })
+}
+
+fn check_exit_status(exit_status: i32, execution: &Execution, result: &mut test::TestResult) {
+    let test_name = "command - exit status";
+    let actual = match execution.status {
+        Some(s) => s.to_string(),
+        None => "killed".to_string()
+    };
+    let condition = execution.status == Some(exit_status);
+    test::update_test_result(condition, test_name, &exit_status.to_string(), &actual, "incorrect exit status", result);
+}
+
+/// an exact match ignores the trailing newline
+fn check_output(stream: &str,
+                actual: &str,
+                exact: Option<&str>,
+                contains: Option<&str>,
+                regex: Option<&Regex>,
+                result: &mut test::TestResult) {
+    let trimmed = actual.trim_end_matches('\n');
+    exact.map(|e| {
+        let test_name = format!("command - {}", stream);
+        test::update_test_result(trimmed == e, &test_name, e, trimmed, "incorrect output", result);
+    });
+    contains.map(|c| {
+        let test_name = format!("command - {} contains", stream);
+        test::update_test_result(actual.contains(c), &test_name, c, trimmed, "output not found", result);
+    });
+    regex.map(|r| {
+        let test_name = format!("command - {} regex", stream);
+        test::update_test_result(r.is_match(actual), &test_name, r.as_str(), trimmed, "output doesn't match", result);
+    });
+}
+
+fn check_timeout(timeout: &Duration, execution: &Execution, result: &mut test::TestResult) {
+    let test_name = "command - timeout";
+    let condition = !execution.timed_out;
+    test::update_test_result(condition, test_name, &util::format_duration(timeout),
+                             &util::format_duration(&execution.runtime), "command timed out", result);
+}
+
+fn check_max_runtime(max_runtime: &Duration, execution: &Execution, result: &mut test::TestResult) {
+    let test_name = "command - max runtime";
+    let condition = execution.runtime <= *max_runtime;
+    test::update_test_result(condition, test_name, &util::format_duration(max_runtime),
+                             &util::format_duration(&execution.runtime), "command too slow", result);
+}
+
+pub fn check(command_line: &str,
+             shell: Option<&str>,
+             username: Option<&str>,
+             env: Option<&str>,
+             timeout: Option<&str>,
+             exit_status: Option<&str>,
+             stdout: Option<&str>,
+             stdout_contains: Option<&str>,
+             stdout_regex: Option<&str>,
+             stderr: Option<&str>,
+             stderr_contains: Option<&str>,
+             stderr_regex: Option<&str>,
+             max_runtime: Option<&str>) -> Result<test::TestResult, CommandError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let shell = try!(util::parse_bool(shell)).unwrap_or(false);
+    let timeout = try!(util::parse_duration(timeout));
+    let max_runtime = try!(util::parse_duration(max_runtime));
+    let exit_status = try!(util::parse_int(exit_status));
+    let stdout_regex = match stdout_regex {
+        Some(r) => Some(try!(Regex::new(r))),
+        None => None
+    };
+    let stderr_regex = match stderr_regex {
+        Some(r) => Some(try!(Regex::new(r))),
+        None => None
+    };
+
+    let mut command = try!(build_command(command_line, shell));
+    if let Some(env) = env {
+        for (key, value) in try!(parse_env(env)) {
+            command.env(key, value);
+        }
+    }
+    if let Some(username) = username {
+        let user = try!(user::get_user(username));
+        test::check_exists(&user, true, &mut result, "command - user".to_string());
+        match user {
+            None => return Ok(result),
+            Some(u) => try!(set_user(&mut command, &u))
+        }
+    }
+
+    let execution = try!(run(&mut command, timeout));
+    timeout.map(|t| {
+        check_timeout(&t, &execution, &mut result);
+    });
+    exit_status.map(|s| {
+        check_exit_status(s, &execution, &mut result);
+    });
+    check_output("stdout", &execution.stdout, stdout, stdout_contains, stdout_regex.as_ref(), &mut result);
+    check_output("stderr", &execution.stderr, stderr, stderr_contains, stderr_regex.as_ref(), &mut result);
+    max_runtime.map(|m| {
+        check_max_runtime(&m, &execution, &mut result);
+    });
+    Ok(result)
+}
+
+// TESTS
+
+#[test]
+fn build_command_test_success() {
+    let execution = run(&mut build_command("echo  hello   world", false).unwrap(), None).unwrap();
+    assert_eq!(execution.stdout, "hello world\n");
+    let execution = run(&mut build_command("echo hello >&2; exit 3", true).unwrap(), None).unwrap();
+    assert_eq!(execution.status, Some(3));
+    assert_eq!(execution.stderr, "hello\n");
+    assert!(build_command("  ", false).is_err());
+}
+
+#[test]
+fn parse_env_test_success() {
+    let env = parse_env("FOO=bar,EMPTY=,EQ=a=b").unwrap();
+    assert_eq!(env, vec![("FOO".to_string(), "bar".to_string()),
+                         ("EMPTY".to_string(), "".to_string()),
+                         ("EQ".to_string(), "a=b".to_string())]);
+    assert!(parse_env("FOO").is_err());
+    assert!(parse_env("=bar").is_err());
+}
+
+#[test]
+fn run_test_timeout() {
+    let mut command = build_command("sleep 5; echo done", true).unwrap();
+    let execution = run(&mut command, Some(Duration::from_millis(200))).unwrap();
+    assert!(execution.timed_out);
+    assert!(execution.status.is_none());
+    assert_eq!(execution.stdout, "");
+    assert!(execution.runtime < Duration::from_secs(5));
+}
+
+#[test]
+fn run_test_background() {
+    // the background sleep keeps stdout open after the shell exits
+    let mut command = build_command("echo started; sleep 5 &", true).unwrap();
+    let start = Instant::now();
+    let execution = run(&mut command, Some(Duration::from_millis(300))).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(execution.status, Some(0));
+    assert_eq!(execution.stdout, "started\n");
+    let mut command = build_command("echo started; sleep 5 &", true).unwrap();
+    let start = Instant::now();
+    let execution = run(&mut command, None).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(execution.stdout, "started\n");
+}
+
+#[test]
+fn get_group_list_test_success() {
+    assert!(get_group_list("root", 0).unwrap().contains(&0));
+    assert_eq!(get_group_list("notexists", 1234).unwrap(), vec![1234]);
+}
+
+#[test]
+fn check_test_user() {
+    // changing of user needs root
+    if unsafe { libc::getuid() } != 0 {
+        return;
+    }
+    let user = user::get_user("nobody").unwrap().unwrap();
+    let groups = get_group_list("nobody", user.gid as libc::gid_t).unwrap();
+    let groups: Vec<String> = groups.iter().map(|g| g.to_string()).collect();
+    let expected = format!("{} {}\nnobody", user.uid, groups.join(" "));
+    let result = check("echo $(id -u) $(id -G); echo $LOGNAME", Some("true"), Some("nobody"), None, Some("5"),
+                       Some("0"), Some(&expected), None, None, None, None, None, None).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 4);
+}
+
+#[test]
+fn check_test_success() {
+    let result = check("echo $GREETING world", Some("true"), None, Some("GREETING=hello"), Some("5"), Some("0"),
+                       Some("hello world"), Some("world"), Some("^hello"), Some(""), None, None, Some("5")).unwrap();
+    assert_eq!(result.success, 7);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_test_error() {
+    let result = check("sleep 2", None, None, None, Some("0.1"), Some("0"), None, None, None, None, None, None,
+                       Some("0.05")).unwrap();
+    assert_eq!(result.error, 3);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "0");
+            assert_eq!(e.actual, "killed");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check("ls /notexists", None, None, None, None, Some("0"), None, None, None, None,
+                       Some("notexists"), None, None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+    let result = check("true", None, Some("notexists"), None, None, None, None, None, None, None, None, None,
+                       None).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check("true", None, None, Some("FOO"), None, None, None, None, None, None, None, None, None).is_err());
+}
@@ -0,0 +1,349 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::str;
+use regex::{self, Regex};
+use modules::test;
+use util;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error),
+    Format(String)
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for ConfigError {
+    fn from(err: str::ParseBoolError) -> ConfigError {
+        ConfigError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for ConfigError {
+    fn from(err: regex::Error) -> ConfigError {
+        ConfigError::Regex(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    /// None outside of a section, and for the formats without sections
+    pub section: Option<String>,
+    pub key: String,
+    pub value: String,
+    pub line: usize
+}
+
+/// reads the entries of a config file, one implementation per format
+pub trait Parser {
+    fn parse(&self, content: &str) -> Vec<Entry>;
+}
+
+/// `[section]` headers, `key = value` or `key: value` lines, `;` and `#` comments, also after
+/// an unquoted value (`expose_php = Off ; hardened`).
+/// Keys without a value (`skip-name-resolve` in my.cnf) have an empty value,
+/// `!include` directives are ignored.
+pub struct IniParser;
+
+impl Parser for IniParser {
+    fn parse(&self, content: &str) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        let mut section = None;
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') || line.starts_with('!') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = Some(line[1..line.len() - 1].trim().to_string());
+                continue;
+            }
+            let (key, value) = match line.find(|c| c == '=' || c == ':') {
+                Some(i) => (line[..i].trim(), unquote(strip_comment(&line[i + 1..]).trim())),
+                None => (line, "")
+            };
+            entries.push(Entry {
+                section: section.clone(),
+                key: key.to_string(),
+                value: value.to_string(),
+                line: i + 1
+            });
+        }
+        entries
+    }
+}
+
+/// `KEY=value`, `KEY="value"` or `KEY='value'`, with an optional `export`, like the sysconfig
+/// and default files sourced by init scripts. Comments after unquoted values are removed.
+pub struct ShellParser;
+
+impl Parser for ShellParser {
+    fn parse(&self, content: &str) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let line = if line.starts_with("export ") { line["export ".len()..].trim_start() } else { line };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let equal = match line.find('=') {
+                Some(e) if e > 0 && !line[..e].contains(char::is_whitespace) => e,
+                _ => continue
+            };
+            entries.push(Entry {
+                section: None,
+                key: line[..equal].to_string(),
+                value: parse_shell_value(&line[equal + 1..]),
+                line: i + 1
+            });
+        }
+        entries
+    }
+}
+
+/// `key value`, the value is the rest of the line (chrony.conf, ntp.conf)
+pub struct WhitespaceParser;
+
+impl Parser for WhitespaceParser {
+    fn parse(&self, content: &str) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let mut fields = line.splitn(2, char::is_whitespace);
+            let key = fields.next().unwrap_or("");
+            let value = fields.next().unwrap_or("").split_whitespace().collect::<Vec<&str>>().join(" ");
+            entries.push(Entry {
+                section: None,
+                key: key.to_string(),
+                value: value,
+                line: i + 1
+            });
+        }
+        entries
+    }
+}
+
+/// the value before a `;` or `#` that starts it or follows a whitespace, outside of quotes
+fn strip_comment(value: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut previous = ' ';
+    for (i, c) in value.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ';') | (None, '#') if previous.is_whitespace() => return &value[..i],
+            _ => ()
+        }
+        previous = c;
+    }
+    value
+}
+
+fn unquote(value: &str) -> &str {
+    if value.len() > 1 && ((value.starts_with('"') && value.ends_with('"'))
+                           || (value.starts_with('\'') && value.ends_with('\''))) {
+        &value[1..value.len() - 1]
+    }
+    else {
+        value
+    }
+}
+
+/// the value of a shell assignment: backslashes escape outside of quotes and in double quotes,
+/// not in single quotes, an unquoted value ends at the first whitespace
+pub fn parse_shell_value(raw: &str) -> String {
+    let mut value = String::new();
+    let mut chars = raw.chars();
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            (None, c) if c.is_whitespace() => break,
+            (_, c) => value.push(c)
+        }
+    }
+    value
+}
+
+/// like most parsers of these formats, the last value of a key wins
+pub fn get_effective<'a>(entries: &'a Vec<Entry>, section: Option<&str>, key: &str) -> Option<&'a Entry> {
+    entries.iter().rev().find(|e| e.key == key && e.section.as_ref().map(|s| s.as_str()) == section)
+}
+
+fn describe(path: &str, key: &str, entry: &Entry) -> String {
+    format!("{}={} ({}:{})", key, entry.value, path, entry.line)
+}
+
+pub fn check_config<P: Parser>(parser: &P,
+                               path: &str,
+                               section: Option<&str>,
+                               key: &str,
+                               exists: Option<&str>,
+                               value: Option<&str>,
+                               regex: Option<&str>) -> Result<test::TestResult, ConfigError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists = try!(util::parse_bool(exists)).unwrap_or(true);
+    let regex = match regex {
+        Some(r) => Some(try!(Regex::new(r))),
+        None => None
+    };
+    let mut content = String::new();
+    try!(try!(fs::File::open(path)).read_to_string(&mut content));
+    let entries = parser.parse(&content);
+    let effective = get_effective(&entries, section, key);
+    test::check_exists(&effective, exists, &mut result, "config - exists".to_string());
+    let entry = match effective {
+        Some(e) => e,
+        None => return Ok(result)
+    };
+    value.map(|v| {
+        test::update_test_result(entry.value == v, "config - value", &format!("{}={}", key, v),
+                                 &describe(path, key, entry), "incorrect value", &mut result);
+    });
+    regex.map(|r| {
+        test::update_test_result(r.is_match(&entry.value), "config - regex", r.as_str(), &describe(path, key, entry),
+                                 "value doesn't match", &mut result);
+    });
+    Ok(result)
+}
+
+/// formats: ini (default), shell or whitespace
+pub fn check(path: &str,
+             format: Option<&str>,
+             section: Option<&str>,
+             key: &str,
+             exists: Option<&str>,
+             value: Option<&str>,
+             regex: Option<&str>) -> Result<test::TestResult, ConfigError> {
+    match format.unwrap_or("ini") {
+        "ini" => check_config(&IniParser, path, section, key, exists, value, regex),
+        "shell" => check_config(&ShellParser, path, section, key, exists, value, regex),
+        "whitespace" => check_config(&WhitespaceParser, path, section, key, exists, value, regex),
+        f => Err(ConfigError::Format(f.to_string()))
+    }
+}
+
+// TESTS
+
+#[cfg(test)]
+const FIXTURES: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/config");
+
+#[cfg(test)]
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+#[cfg(test)]
+fn read_fixture(name: &str) -> String {
+    let mut content = String::new();
+    fs::File::open(fixture(name)).unwrap().read_to_string(&mut content).unwrap();
+    content
+}
+
+#[test]
+fn ini_parser_test_success() {
+    let entries = IniParser.parse(&read_fixture("php.ini"));
+    assert_eq!(entries.len(), 9);
+    assert_eq!(entries[0].section, Some("PHP".to_string()));
+    assert_eq!(entries[4].value, "E_ALL & ~E_DEPRECATED & ~E_STRICT");
+    assert_eq!(entries[5].value, "");
+    assert_eq!(get_effective(&entries, Some("PHP"), "expose_php").unwrap().value, "Off");
+    assert_eq!(get_effective(&entries, Some("Date"), "date.timezone").unwrap().line, 14);
+    assert!(get_effective(&entries, None, "engine").is_none());
+    let entries = IniParser.parse(&read_fixture("my.cnf"));
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[4].key, "skip-name-resolve");
+    assert_eq!(entries[4].value, "");
+    assert_eq!(get_effective(&entries, Some("mysqld"), "bind-address").unwrap().value, "127.0.0.1");
+    let entries = IniParser.parse("a = \"x ; y\" ; quoted\nb = 'x # y'\nc = x;y#z\nd = ; empty\n");
+    assert_eq!(entries[0].value, "x ; y");
+    assert_eq!(entries[1].value, "x # y");
+    assert_eq!(entries[2].value, "x;y#z");
+    assert_eq!(entries[3].value, "");
+}
+
+#[test]
+fn shell_parser_test_success() {
+    let entries = ShellParser.parse(&read_fixture("nginx.sysconfig"));
+    assert_eq!(entries.len(), 7);
+    assert_eq!(entries[2].key, "OPTIONS");
+    assert_eq!(entries[2].value, "-g \"daemon off;\"");
+    assert_eq!(entries[3].value, "warn");
+    assert_eq!(entries[4].value, "");
+    assert_eq!(get_effective(&entries, None, "CONFFILE").unwrap().value, "/etc/nginx/custom.conf");
+    assert_eq!(get_effective(&entries, None, "ESCAPED").unwrap().value, "say \"hi\" # not a comment");
+}
+
+#[test]
+fn whitespace_parser_test_success() {
+    let entries = WhitespaceParser.parse(&read_fixture("chrony.conf"));
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].key, "pool");
+    assert_eq!(entries[0].value, "2.debian.pool.ntp.org iburst");
+    assert_eq!(entries[2].value, "1.0 3");
+    assert_eq!(entries[3].value, "");
+}
+
+#[test]
+fn check_test_success() {
+    let result = check(&fixture("php.ini"), None, Some("PHP"), "expose_php", None, Some("Off"), Some("^(Off|0)$"))
+        .unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check(&fixture("my.cnf"), Some("ini"), Some("mysqld"), "skip-name-resolve", Some("true"), None, None)
+        .unwrap();
+    assert_eq!(result.success, 1);
+    let result = check(&fixture("my.cnf"), Some("ini"), Some("client"), "bind-address", Some("false"), None, None)
+        .unwrap();
+    assert_eq!(result.success, 1);
+    let result = check(&fixture("nginx.sysconfig"), Some("shell"), None, "CONFFILE", None, None, Some("custom"))
+        .unwrap();
+    assert_eq!(result.success, 2);
+    let result = check(&fixture("chrony.conf"), Some("whitespace"), None, "makestep", None, Some("1.0 3"), None)
+        .unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_test_error() {
+    let result = check(&fixture("my.cnf"), None, Some("mysqld"), "bind-address", Some("false"), Some("0.0.0.0"),
+                       None).unwrap();
+    assert_eq!(result.error, 2);
+    let result = check(&fixture("my.cnf"), None, Some("mysqld"), "max_connections", None, Some("1000"),
+                       Some("^[0-9]{4}$")).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 2);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "max_connections=1000");
+            assert_eq!(e.actual, format!("max_connections=500 ({}:10)", fixture("my.cnf")));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check(&fixture("php.ini"), None, None, "engine", None, Some("On"), None).unwrap();
+    assert_eq!(result.error, 1);
+    assert_eq!(result.summary.len(), 1);
+    assert!(check(&fixture("php.ini"), Some("toml"), None, "engine", None, None, None).is_err());
+    assert!(check(&fixture("php.ini"), None, None, "engine", None, None, Some("(")).is_err());
+    assert!(check(&fixture("notexists.ini"), None, None, "engine", None, None, None).is_err());
+}
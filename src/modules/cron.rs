@@ -0,0 +1,375 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::os::unix::fs::PermissionsExt;
+use std::str;
+use regex::{self, Regex};
+use modules::test;
+use util;
+
+const CRONTAB: &'static str = "etc/crontab";
+const CRON_D: &'static str = "etc/cron.d";
+/// Debian spool first, then the Red Hat one
+const SPOOLS: [&'static str; 2] = ["var/spool/cron/crontabs", "var/spool/cron"];
+const PERIODIC_DIRS: [(&'static str, &'static str); 4] = [("etc/cron.hourly", "@hourly"), ("etc/cron.daily", "@daily"),
+                                                          ("etc/cron.weekly", "@weekly"), ("etc/cron.monthly", "@monthly")];
+const MONTHS: [&'static str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const DAYS: [&'static str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug)]
+pub enum CronError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error)
+}
+
+impl From<io::Error> for CronError {
+    fn from(err: io::Error) -> CronError {
+        CronError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for CronError {
+    fn from(err: str::ParseBoolError) -> CronError {
+        CronError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for CronError {
+    fn from(err: regex::Error) -> CronError {
+        CronError::Regex(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Job {
+    pub user: String,
+    /// normalized, see normalize_schedule
+    pub schedule: String,
+    pub command: String,
+    pub file: String,
+    pub line: usize
+}
+
+impl Job {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+fn normalize_field(field: &str, names: &[&str], offset: usize) -> String {
+    let mut field = field.to_lowercase();
+    for (i, name) in names.iter().enumerate() {
+        field = field.replace(name, &(i + offset).to_string());
+    }
+    if field.ends_with("/1") {
+        let len = field.len();
+        field.truncate(len - 2);
+    }
+    field
+}
+
+/// macros are expanded (`@daily` is `0 0 * * *`), month and day names are replaced by numbers
+/// and Sunday is always 0. @reboot is kept as is.
+pub fn normalize_schedule(schedule: &str) -> String {
+    let schedule = match schedule.to_lowercase().as_str() {
+        "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+        "@monthly" => "0 0 1 * *".to_string(),
+        "@weekly" => "0 0 * * 0".to_string(),
+        "@daily" | "@midnight" => "0 0 * * *".to_string(),
+        "@hourly" => "0 * * * *".to_string(),
+        _ => schedule.to_string()
+    };
+    let fields: Vec<&str> = schedule.split_whitespace().collect();
+    if fields.len() != 5 {
+        return fields.join(" ");
+    }
+    let day_of_week = normalize_field(fields[4], &DAYS, 0);
+    let day_of_week = if day_of_week == "7" { "0".to_string() } else { day_of_week };
+    format!("{} {} {} {} {}", normalize_field(fields[0], &[], 0), normalize_field(fields[1], &[], 0),
+            normalize_field(fields[2], &[], 0), normalize_field(fields[3], &MONTHS, 1), day_of_week)
+}
+
+/// the first `count` whitespace separated fields, then the rest of the line untouched
+fn split_fields(line: &str, count: usize) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim();
+    while fields.len() < count && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() {
+        fields.push(rest);
+    }
+    fields
+}
+
+/// system crontabs have a user field, user crontabs don't (the file is named after the user)
+pub fn parse_crontab(path: &str, user: Option<&str>) -> Result<Vec<Job>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut jobs = Vec::new();
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let line = line.trim();
+        let first = line.split_whitespace().next().unwrap_or("");
+        if line.is_empty() || line.starts_with('#') || (first.contains('=') && !first.starts_with('@')) {
+            continue;
+        }
+        let schedule_fields = if first.starts_with('@') { 1 } else { 5 };
+        let user_fields = if user.is_some() { 0 } else { 1 };
+        let fields = split_fields(line, schedule_fields + user_fields);
+        if fields.len() <= schedule_fields + user_fields {
+            continue;
+        }
+        jobs.push(Job {
+            user: user.map_or(fields[schedule_fields].to_string(), |u| u.to_string()),
+            schedule: normalize_schedule(&fields[..schedule_fields].join(" ")),
+            command: fields[schedule_fields + user_fields].to_string(),
+            file: path.to_string(),
+            line: i + 1
+        });
+    }
+    Ok(jobs)
+}
+
+/// like run-parts, only names made of letters, digits, `_` and `-` are used
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// a missing directory is empty, an unreadable one (the spool of Debian is only readable by
+/// the crontab group) is an error, not an empty list
+fn list_dir(dir: &str) -> Result<Vec<(String, fs::Metadata)>, io::Error> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e)
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = try!(entry);
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let metadata = try!(entry.metadata());
+        if metadata.is_file() && is_valid_name(&name) {
+            files.push((name, metadata));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// users having a crontab in the spool
+pub fn get_crontab_users(root: &str) -> Result<Vec<(String, String)>, io::Error> {
+    let mut users = Vec::new();
+    for spool in SPOOLS.iter() {
+        let dir = util::join_root(root, spool);
+        for (name, _) in try!(list_dir(&dir)) {
+            users.push((name.clone(), format!("{}/{}", dir, name)));
+        }
+    }
+    Ok(users)
+}
+
+/// jobs of /etc/crontab, /etc/cron.d, the user spools and the executable scripts of the
+/// periodic directories (run by root, with the schedule of the directory)
+pub fn get_jobs(root: &str) -> Result<Vec<Job>, io::Error> {
+    let mut jobs = Vec::new();
+    let crontab = util::join_root(root, CRONTAB);
+    if fs::metadata(&crontab).is_ok() {
+        jobs.extend(try!(parse_crontab(&crontab, None)));
+    }
+    let cron_d = util::join_root(root, CRON_D);
+    for (name, _) in try!(list_dir(&cron_d)) {
+        jobs.extend(try!(parse_crontab(&format!("{}/{}", cron_d, name), None)));
+    }
+    for (user, path) in try!(get_crontab_users(root)) {
+        jobs.extend(try!(parse_crontab(&path, Some(&user))));
+    }
+    for &(dir, schedule) in PERIODIC_DIRS.iter() {
+        let dir = util::join_root(root, dir);
+        for (name, metadata) in try!(list_dir(&dir)) {
+            if metadata.permissions().mode() & 0o111 != 0 {
+                jobs.push(Job {
+                    user: "root".to_string(),
+                    schedule: normalize_schedule(schedule),
+                    command: format!("{}/{}", dir, name),
+                    file: dir.clone(),
+                    line: 0
+                });
+            }
+        }
+    }
+    Ok(jobs)
+}
+
+fn check_job(command: &Regex,
+             user: Option<&str>,
+             schedule: Option<&str>,
+             exists: bool,
+             jobs: &Vec<Job>,
+             result: &mut test::TestResult) {
+    let matched: Vec<&Job> = jobs.iter()
+        .filter(|j| command.is_match(&j.command) && user.map_or(true, |u| j.user == u))
+        .collect();
+    let found = if matched.is_empty() { None } else { Some(()) };
+    test::check_exists(&found, exists, result, "cron - job".to_string());
+    if let (Some(schedule), false) = (schedule, matched.is_empty()) {
+        let test_name = "cron - schedule";
+        let normalized = normalize_schedule(schedule);
+        let condition = matched.iter().any(|j| j.schedule == normalized);
+        let actual: Vec<String> = matched.iter().map(|j| format!("{} ({})", j.schedule, j.location())).collect();
+        test::update_test_result(condition, test_name, &normalized, &actual.join(","), "incorrect schedule", result);
+    }
+}
+
+/// one error per user outside of the allow-list
+fn check_crontab_users(allowed: &str, users: &Vec<(String, String)>, result: &mut test::TestResult) {
+    let test_name = "cron - crontab users";
+    let allowed_list: Vec<&str> = allowed.split(',').filter(|a| !a.is_empty()).collect();
+    let denied: Vec<&(String, String)> = users.iter().filter(|u| !allowed_list.contains(&u.0.as_str())).collect();
+    for &&(ref user, ref path) in &denied {
+        test::update_test_result(false, test_name, allowed, &format!("{} ({})", user, path), "user not allowed", result);
+    }
+    if denied.is_empty() {
+        test::update_test_result(true, test_name, allowed, "", "", result);
+    }
+}
+
+pub fn check_cron(root: &str,
+                  command: Option<&str>,
+                  user: Option<&str>,
+                  schedule: Option<&str>,
+                  exists: Option<&str>,
+                  allowed_users: Option<&str>) -> Result<test::TestResult, CronError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists = try!(util::parse_bool(exists)).unwrap_or(true);
+    if let Some(command) = command {
+        let command = try!(Regex::new(command));
+        let jobs = try!(get_jobs(root));
+        check_job(&command, user, schedule, exists, &jobs, &mut result);
+    }
+    if let Some(allowed_users) = allowed_users {
+        let users = try!(get_crontab_users(root));
+        check_crontab_users(allowed_users, &users, &mut result);
+    }
+    Ok(result)
+}
+
+pub fn check(command: Option<&str>,
+             user: Option<&str>,
+             schedule: Option<&str>,
+             exists: Option<&str>,
+             allowed_users: Option<&str>) -> Result<test::TestResult, CronError> {
+    check_cron("/", command, user, schedule, exists, allowed_users)
+}
+
+// TESTS
+
+#[cfg(test)]
+const ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/cron");
+
+#[test]
+fn normalize_schedule_test_success() {
+    assert_eq!(normalize_schedule("@daily"), "0 0 * * *");
+    assert_eq!(normalize_schedule("@ANNUALLY"), "0 0 1 1 *");
+    assert_eq!(normalize_schedule("0  0 1 JAN *"), "0 0 1 1 *");
+    assert_eq!(normalize_schedule("*/1 * * * sun-sat"), "* * * * 0-6");
+    assert_eq!(normalize_schedule("47 6 * * 7"), "47 6 * * 0");
+    assert_eq!(normalize_schedule("@reboot"), "@reboot");
+}
+
+#[test]
+fn parse_crontab_test_success() {
+    let jobs = parse_crontab(&format!("{}/etc/crontab", ROOT_FIXTURE), None).unwrap();
+    assert_eq!(jobs.len(), 3);
+    assert_eq!(jobs[0].user, "root");
+    assert_eq!(jobs[0].schedule, "17 * * * *");
+    assert_eq!(jobs[0].command, "cd / && run-parts --report /etc/cron.hourly");
+    assert_eq!(jobs[2].line, 8);
+    let jobs = parse_crontab(&format!("{}/var/spool/cron/crontabs/alice", ROOT_FIXTURE), Some("alice")).unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0].schedule, "*/15 * * * *");
+    assert_eq!(jobs[0].command, "/home/alice/bin/sync.sh");
+    assert_eq!(jobs[1].schedule, "@reboot");
+}
+
+#[test]
+fn get_jobs_test_success() {
+    let jobs = get_jobs(ROOT_FIXTURE).unwrap();
+    assert_eq!(jobs.len(), 10);
+    assert!(jobs.iter().all(|j| !j.command.contains("ignored") && !j.command.contains("disabled")));
+    let job = jobs.iter().find(|j| j.command.ends_with("cron.daily/logrotate")).unwrap();
+    assert_eq!(job.user, "root");
+    assert_eq!(job.schedule, "0 0 * * *");
+    let users = get_crontab_users(ROOT_FIXTURE).unwrap();
+    assert_eq!(users.iter().map(|u| u.0.as_str()).collect::<Vec<&str>>(), vec!["alice", "root"]);
+}
+
+#[test]
+fn get_crontab_users_test_error() {
+    use libc;
+    let root = format!("{}/lothaire-cron-unreadable", ::std::env::temp_dir().display());
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(format!("{}/var/spool/cron/crontabs", root)).unwrap();
+    fs::set_permissions(format!("{}/var/spool/cron/crontabs", root), fs::Permissions::from_mode(0o000)).unwrap();
+    // root can read the directory anyway
+    if unsafe { libc::geteuid() } != 0 {
+        assert!(get_crontab_users(&root).is_err());
+        assert!(check_cron(&root, None, None, None, None, Some("root")).is_err());
+    }
+    fs::set_permissions(format!("{}/var/spool/cron/crontabs", root), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    // a file instead of the spool directory can't be read either
+    fs::create_dir_all(format!("{}/var/spool", root)).unwrap();
+    fs::File::create(format!("{}/var/spool/cron", root)).unwrap();
+    assert!(get_crontab_users(&root).is_err());
+    assert!(check_cron(&root, None, None, None, None, Some("root")).is_err());
+    assert!(get_crontab_users("/notexists").unwrap().is_empty());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn check_cron_test_success() {
+    let result = check_cron(ROOT_FIXTURE, Some("backup\\.sh --full"), Some("backup"), Some("0 0 * * *"), None,
+                            Some("root,alice")).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check_cron(ROOT_FIXTURE, Some("certbot"), Some("root"), Some("0 */12 * * *"), Some("true"),
+                            None).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check_cron(ROOT_FIXTURE, Some("yearly-report"), Some("root"), Some("@yearly"), None, None).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check_cron(ROOT_FIXTURE, Some("ignored"), None, None, Some("false"), None).unwrap();
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_cron_test_error() {
+    let result = check_cron(ROOT_FIXTURE, Some("backup\\.sh"), Some("backup"), Some("@hourly"), None,
+                            Some("root")).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 2);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "0 * * * *");
+            assert_eq!(e.actual, format!("0 0 * * * ({0}/etc/cron.d/backup:1),30 2 * * 0-6 ({0}/etc/cron.d/backup:2)",
+                                         ROOT_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[2] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.actual, format!("alice ({}/var/spool/cron/crontabs/alice)", ROOT_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_cron(ROOT_FIXTURE, Some("sync"), Some("root"), None, None, None).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check_cron(ROOT_FIXTURE, Some("("), None, None, None, None).is_err());
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::ToSocketAddrs;
+use std::str;
+use modules::test;
+use util;
+
+const HOSTS: &'static str = "/etc/hosts";
+const RESOLV_CONF: &'static str = "/etc/resolv.conf";
+
+#[derive(Debug)]
+pub enum DnsError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError)
+}
+
+impl From<io::Error> for DnsError {
+    fn from(err: io::Error) -> DnsError {
+        DnsError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for DnsError {
+    fn from(err: str::ParseBoolError) -> DnsError {
+        DnsError::ParseBool(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct ResolvConf {
+    pub nameservers: Vec<String>,
+    pub search: Vec<String>,
+    pub options: Vec<String>
+}
+
+/// resolves a name with the system resolver (getaddrinfo), returns the sorted addresses
+/// an empty list means the name can't be resolved
+pub fn resolve(name: &str) -> Vec<String> {
+    let mut addresses: Vec<String> = match (name, 0).to_socket_addrs() {
+        Ok(addrs) => addrs.map(|a| a.ip().to_string()).collect(),
+        Err(_) => Vec::new()
+    };
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+/// returns the addresses /etc/hosts gives to a name (or alias)
+pub fn get_hosts_addresses(path: &str, name: &str) -> Result<Vec<String>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let name = name.to_lowercase();
+    let mut addresses = Vec::new();
+    for l in reader.lines() {
+        let line = try!(l);
+        let line = line.split('#').next().unwrap_or("");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            continue;
+        }
+        if fields[1..].iter().any(|f| f.to_lowercase() == name) && !addresses.contains(&fields[0].to_string()) {
+            addresses.push(fields[0].to_string());
+        }
+    }
+    Ok(addresses)
+}
+
+/// parses resolv.conf; like the resolver, the last `search` or `domain` line wins
+pub fn get_resolv_conf(path: &str) -> Result<ResolvConf, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut resolv_conf = ResolvConf {
+        nameservers: Vec::new(),
+        search: Vec::new(),
+        options: Vec::new()
+    };
+    for l in reader.lines() {
+        let line = try!(l);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0].starts_with('#') || fields[0].starts_with(';') {
+            continue;
+        }
+        let values: Vec<String> = fields[1..].iter().map(|f| f.to_string()).collect();
+        match fields[0] {
+            "nameserver" => resolv_conf.nameservers.extend(values.into_iter().take(1)),
+            "search" | "domain" => resolv_conf.search = values,
+            "options" => resolv_conf.options.extend(values),
+            _ => {}
+        }
+    }
+    Ok(resolv_conf)
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect()
+}
+
+fn check_addresses(test_name: &str, expected: &str, actual: &Vec<String>, result: &mut test::TestResult) {
+    let mut expected_list = split_list(expected);
+    expected_list.sort();
+    let mut actual_list = actual.clone();
+    actual_list.sort();
+    test::update_test_result(expected_list == actual_list, test_name, expected, &actual.join(","),
+                             "incorrect addresses", result);
+}
+
+fn check_list(test_name: &str, expected: &str, actual: &Vec<String>, message: &str, result: &mut test::TestResult) {
+    let condition = split_list(expected) == *actual;
+    test::update_test_result(condition, test_name, expected, &actual.join(","), message, result);
+}
+
+pub fn check_resolve(name: &str, resolvable: Option<&str>, addresses: Option<&str>) -> Result<test::TestResult, DnsError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let resolvable = try!(util::parse_bool(resolvable));
+    let resolved = resolve(name);
+    if let Some(resolvable) = resolvable {
+        let found = if resolved.is_empty() { None } else { Some(()) };
+        test::check_exists(&found, resolvable, &mut result, "dns - resolvable".to_string());
+    }
+    addresses.map(|a| {
+        check_addresses("dns - addresses", a, &resolved, &mut result);
+    });
+    Ok(result)
+}
+
+pub fn check_hosts_file(path: &str, name: &str, exists: Option<&str>, addresses: Option<&str>) -> Result<test::TestResult, DnsError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists = try!(util::parse_bool(exists));
+    let hosts_addresses = try!(get_hosts_addresses(path, name));
+    if let Some(exists) = exists {
+        let found = if hosts_addresses.is_empty() { None } else { Some(()) };
+        test::check_exists(&found, exists, &mut result, "dns - hosts entry".to_string());
+    }
+    addresses.map(|a| {
+        check_addresses("dns - hosts addresses", a, &hosts_addresses, &mut result);
+    });
+    Ok(result)
+}
+
+pub fn check_resolv_conf_file(path: &str,
+                              nameservers: Option<&str>,
+                              search: Option<&str>,
+                              options: Option<&str>) -> Result<test::TestResult, DnsError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let resolv_conf = try!(get_resolv_conf(path));
+    nameservers.map(|n| {
+        check_list("dns - nameservers", n, &resolv_conf.nameservers, "incorrect nameservers", &mut result);
+    });
+    search.map(|s| {
+        check_list("dns - search", s, &resolv_conf.search, "incorrect search domains", &mut result);
+    });
+    if let Some(options) = options {
+        let actual = resolv_conf.options.join(",");
+        for option in split_list(options) {
+            let condition = resolv_conf.options.contains(&option);
+            test::update_test_result(condition, "dns - options", &option, &actual, "missing option", &mut result);
+        }
+    }
+    Ok(result)
+}
+
+pub fn check_hosts(name: &str, exists: Option<&str>, addresses: Option<&str>) -> Result<test::TestResult, DnsError> {
+    check_hosts_file(HOSTS, name, exists, addresses)
+}
+
+pub fn check_resolv_conf(nameservers: Option<&str>,
+                         search: Option<&str>,
+                         options: Option<&str>) -> Result<test::TestResult, DnsError> {
+    check_resolv_conf_file(RESOLV_CONF, nameservers, search, options)
+}
+
+// TESTS
+
+#[cfg(test)]
+const HOSTS_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/dns/hosts");
+#[cfg(test)]
+const RESOLV_CONF_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/dns/resolv.conf");
+
+#[test]
+fn resolve_test_success() {
+    let addresses = resolve("localhost");
+    assert!(addresses.contains(&"127.0.0.1".to_string()) || addresses.contains(&"::1".to_string()));
+    assert!(resolve("notexists.invalid").is_empty());
+}
+
+#[test]
+fn get_hosts_addresses_test_success() {
+    assert_eq!(get_hosts_addresses(HOSTS_FIXTURE, "localhost").unwrap(), vec!["127.0.0.1", "::1"]);
+    assert_eq!(get_hosts_addresses(HOSTS_FIXTURE, "DB").unwrap(), vec!["10.0.0.10"]);
+    assert_eq!(get_hosts_addresses(HOSTS_FIXTURE, "db.internal").unwrap(), vec!["10.0.0.10", "10.0.0.11"]);
+    assert!(get_hosts_addresses(HOSTS_FIXTURE, "primary").unwrap().is_empty());
+}
+
+#[test]
+fn get_resolv_conf_test_success() {
+    let resolv_conf = get_resolv_conf(RESOLV_CONF_FIXTURE).unwrap();
+    assert_eq!(resolv_conf.nameservers, vec!["10.0.0.2", "10.0.0.3"]);
+    assert_eq!(resolv_conf.search, vec!["example.com", "internal"]);
+    assert_eq!(resolv_conf.options, vec!["timeout:2", "attempts:3", "rotate"]);
+}
+
+#[test]
+fn check_resolve_test_success() {
+    let result = check_resolve("localhost", Some("true"), None).unwrap();
+    assert_eq!(result.success, 1);
+    let result = check_resolve("notexists.invalid", Some("false"), Some("")).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check_resolve("notexists.invalid", Some("true"), None).unwrap();
+    assert_eq!(result.error, 1);
+}
+
+#[test]
+fn check_hosts_file_test_success() {
+    let result = check_hosts_file(HOSTS_FIXTURE, "db.internal", Some("true"), Some("10.0.0.11,10.0.0.10")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check_hosts_file(HOSTS_FIXTURE, "old-cache.internal", Some("false"), None).unwrap();
+    assert_eq!(result.error, 1);
+    let result = check_hosts_file(HOSTS_FIXTURE, "db.internal", None, Some("10.0.0.10")).unwrap();
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "10.0.0.10");
+            assert_eq!(e.actual, "10.0.0.10,10.0.0.11");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+}
+
+#[test]
+fn check_resolv_conf_file_test_success() {
+    let result = check_resolv_conf_file(RESOLV_CONF_FIXTURE, Some("10.0.0.2,10.0.0.3"), Some("example.com,internal"),
+                                        Some("rotate,timeout:2")).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    let result = check_resolv_conf_file(RESOLV_CONF_FIXTURE, Some("10.0.0.3,10.0.0.2"), None, Some("edns0")).unwrap();
+    assert_eq!(result.error, 2);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "edns0");
+            assert_eq!(e.actual, "timeout:2,attempts:3,rotate");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+}
@@ -0,0 +1,194 @@
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::num;
+use libc;
+use modules::test;
+
+#[derive(Debug)]
+pub enum UsageError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    ParseFloat(num::ParseFloatError),
+    Threshold(String)
+}
+
+impl From<io::Error> for UsageError {
+    fn from(err: io::Error) -> UsageError {
+        UsageError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for UsageError {
+    fn from(err: num::ParseIntError) -> UsageError {
+        UsageError::ParseInt(err)
+    }
+}
+
+impl From<num::ParseFloatError> for UsageError {
+    fn from(err: num::ParseFloatError) -> UsageError {
+        UsageError::ParseFloat(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Usage {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Threshold {
+    Absolute(u64),
+    Percent(f64)
+}
+
+/// returns the usage of the filesystem containing `path`
+/// free bytes are the bytes available to unprivileged users, like df(1)
+pub fn get_usage(path: &str) -> Result<Usage, io::Error> {
+    let c_path = try!(CString::new(path)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)));
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    let ret = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Usage {
+        total_bytes: stat.f_blocks as u64 * stat.f_frsize as u64,
+        free_bytes: stat.f_bavail as u64 * stat.f_frsize as u64,
+        total_inodes: stat.f_files as u64,
+        free_inodes: stat.f_ffree as u64
+    })
+}
+
+/// parses a threshold: `10%`, `1024`, or with `units` a size like `512M` or `2G`
+/// percentages are between 0 and 100, sizes can't overflow a u64
+pub fn parse_threshold(value: &str, units: bool) -> Result<Threshold, UsageError> {
+    let value = value.trim();
+    if value.ends_with('%') {
+        let percent = try!(value[..value.len() - 1].parse::<f64>());
+        if !(percent >= 0.0 && percent <= 100.0) {
+            return Err(UsageError::Threshold(value.to_string()));
+        }
+        return Ok(Threshold::Percent(percent));
+    }
+    let (number, multiplier) = match value.chars().last() {
+        Some('K') if units => (&value[..value.len() - 1], 1024),
+        Some('M') if units => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') if units => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        Some('T') if units => (&value[..value.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (value, 1)
+    };
+    let number = try!(number.parse::<u64>());
+    match number.checked_mul(multiplier) {
+        Some(bytes) => Ok(Threshold::Absolute(bytes)),
+        None => Err(UsageError::Threshold(value.to_string()))
+    }
+}
+
+fn percent(free: u64, total: u64) -> f64 {
+    if total == 0 {
+        // filesystems without a fixed inode table (btrfs...) report 0
+        100.0
+    }
+    else {
+        free as f64 * 100.0 / total as f64
+    }
+}
+
+fn check_threshold(threshold: &Threshold, free: u64, total: u64, unit: &str, result: &mut test::TestResult) {
+    let test_name = format!("filesystem_usage - free {}", unit);
+    let free_percent = percent(free, total);
+    let (condition, expected) = match *threshold {
+        Threshold::Absolute(min) => (free >= min, format!("free {} >= {}", unit, min)),
+        Threshold::Percent(min) => (free_percent >= min, format!("free {} >= {}%", unit, min))
+    };
+    let actual = format!("free {}: {} ({:.1}% of {})", unit, free, free_percent, total);
+    let message = format!("not enough free {}", unit);
+    test::update_test_result(condition, &test_name, &expected, &actual, &message, result);
+}
+
+pub fn check(path: &str, min_free: Option<&str>, min_free_inodes: Option<&str>) -> Result<test::TestResult, UsageError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let usage = try!(get_usage(path));
+    if let Some(min_free) = min_free {
+        let threshold = try!(parse_threshold(min_free, true));
+        check_threshold(&threshold, usage.free_bytes, usage.total_bytes, "bytes", &mut result);
+    }
+    if let Some(min_free_inodes) = min_free_inodes {
+        let threshold = try!(parse_threshold(min_free_inodes, false));
+        check_threshold(&threshold, usage.free_inodes, usage.total_inodes, "inodes", &mut result);
+    }
+    Ok(result)
+}
+
+// TESTS
+
+#[test]
+fn parse_threshold_test_success() {
+    assert_eq!(parse_threshold("10%", true).unwrap(), Threshold::Percent(10.0));
+    assert_eq!(parse_threshold("2.5%", false).unwrap(), Threshold::Percent(2.5));
+    assert_eq!(parse_threshold("1024", true).unwrap(), Threshold::Absolute(1024));
+    assert_eq!(parse_threshold("512M", true).unwrap(), Threshold::Absolute(512 * 1024 * 1024));
+    assert_eq!(parse_threshold("2G", true).unwrap(), Threshold::Absolute(2 * 1024 * 1024 * 1024));
+}
+
+#[test]
+fn parse_threshold_test_error() {
+    assert!(parse_threshold("hello", true).is_err());
+    assert!(parse_threshold("10G", false).is_err());
+    assert!(parse_threshold("a%", true).is_err());
+    assert!(parse_threshold("101%", true).is_err());
+    assert!(parse_threshold("-1%", true).is_err());
+    assert!(parse_threshold("NaN%", true).is_err());
+    assert!(parse_threshold("99999999999T", true).is_err());
+}
+
+#[test]
+fn get_usage_test_success() {
+    let usage = get_usage("/").unwrap();
+    assert!(usage.total_bytes > 0);
+    assert!(usage.free_bytes <= usage.total_bytes);
+    assert!(get_usage("/notexists").is_err());
+}
+
+#[test]
+fn check_threshold_test_success() {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    check_threshold(&Threshold::Percent(10.0), 50, 1000, "bytes", &mut result);
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "filesystem_usage - free bytes");
+            assert_eq!(e.expected, "free bytes >= 10%");
+            assert_eq!(e.actual, "free bytes: 50 (5.0% of 1000)");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    check_threshold(&Threshold::Absolute(50), 50, 1000, "inodes", &mut result);
+    assert_eq!(result.success, 1);
+    check_threshold(&Threshold::Percent(10.0), 0, 0, "inodes", &mut result);
+    assert_eq!(result.success, 2);
+    check_threshold(&Threshold::Absolute(51), 50, 1000, "bytes", &mut result);
+    assert_eq!(result.error, 2);
+}
+
+#[test]
+fn check_test_success() {
+    let result = check("/", Some("0%"), Some("0")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check("/", Some("100T"), None).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check("/", Some("hello"), None).is_err());
+}
@@ -0,0 +1,796 @@
+use std::io;
+use std::io::prelude::*;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::num;
+use std::str;
+use std::thread;
+use std::time::{Duration, Instant};
+use openssl::error::ErrorStack;
+use openssl::ssl::{SslConnector, SslMethod, SslStream, SslVerifyMode};
+use regex::{self, Regex};
+use rustc_serialize::json::Json;
+use modules::structured_file::{self, Step, StructuredFileError};
+use modules::test;
+use util;
+
+/// bigger bodies are refused, so a broken server can't exhaust the memory
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum HttpError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    Duration(util::DurationError),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error),
+    Ssl(ErrorStack),
+    Tls(String),
+    Url(String),
+    Header(String),
+    Response(String),
+    Query(StructuredFileError)
+}
+
+impl From<io::Error> for HttpError {
+    fn from(err: io::Error) -> HttpError {
+        HttpError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for HttpError {
+    fn from(err: num::ParseIntError) -> HttpError {
+        HttpError::ParseInt(err)
+    }
+}
+
+impl From<util::DurationError> for HttpError {
+    fn from(err: util::DurationError) -> HttpError {
+        HttpError::Duration(err)
+    }
+}
+
+impl From<str::ParseBoolError> for HttpError {
+    fn from(err: str::ParseBoolError) -> HttpError {
+        HttpError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for HttpError {
+    fn from(err: regex::Error) -> HttpError {
+        HttpError::Regex(err)
+    }
+}
+
+impl From<ErrorStack> for HttpError {
+    fn from(err: ErrorStack) -> HttpError {
+        HttpError::Ssl(err)
+    }
+}
+
+impl From<StructuredFileError> for HttpError {
+    fn from(err: StructuredFileError) -> HttpError {
+        HttpError::Query(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Url {
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    pub path: String
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub url: String,
+    pub method: String,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Duration,
+    pub max_redirects: i32,
+    pub ca_file: Option<String>,
+    pub tls_verify: bool
+}
+
+/// the options of the http command, as given on the command line
+#[derive(Debug, Default)]
+pub struct Options<'a> {
+    pub url: &'a str,
+    pub method: Option<&'a str>,
+    pub request_headers: &'a [&'a str],
+    pub timeout: Option<&'a str>,
+    pub retries: Option<&'a str>,
+    pub retry_delay: Option<&'a str>,
+    pub max_redirects: Option<&'a str>,
+    pub ca_file: Option<&'a str>,
+    pub tls_verify: Option<&'a str>,
+    pub status: Option<&'a str>,
+    pub max_time: Option<&'a str>,
+    pub headers: &'a [&'a str],
+    pub body_contains: Option<&'a str>,
+    pub body_regex: Option<&'a str>,
+    pub json: &'a [&'a str]
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: i32,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub time: Duration
+}
+
+impl Response {
+    /// header names are case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|h| h.0.to_lowercase() == name.to_lowercase())
+            .map(|h| h.1.as_str())
+    }
+}
+
+enum Connection {
+    Plain(TcpStream),
+    Tls(SslStream<TcpStream>)
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Connection::Plain(ref mut s) => s.read(buf),
+            Connection::Tls(ref mut s) => s.read(buf)
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Connection::Plain(ref mut s) => s.write(buf),
+            Connection::Tls(ref mut s) => s.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Connection::Plain(ref mut s) => s.flush(),
+            Connection::Tls(ref mut s) => s.flush()
+        }
+    }
+}
+
+/// parses `http(s)://host[:port][/path]`, IPv6 hosts are written `[::1]`
+pub fn parse_url(url: &str) -> Result<Url, HttpError> {
+    let (scheme, rest) = match url.find("://") {
+        Some(i) => (url[..i].to_lowercase(), &url[i + 3..]),
+        None => return Err(HttpError::Url(url.to_string()))
+    };
+    let default_port = match scheme.as_str() {
+        "http" => 80,
+        "https" => 443,
+        _ => return Err(HttpError::Url(url.to_string()))
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/")
+    };
+    let port_separator = if authority.starts_with('[') {
+        authority.find(']').and_then(|i| authority[i..].find(':').map(|j| i + j))
+    }
+    else {
+        authority.rfind(':')
+    };
+    let (host, port) = match port_separator {
+        Some(i) => (&authority[..i], try!(authority[i + 1..].parse::<u16>())),
+        None => (authority, default_port)
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(HttpError::Url(url.to_string()));
+    }
+    Ok(Url {
+        scheme: scheme,
+        host: host.to_string(),
+        port: port,
+        path: path.to_string()
+    })
+}
+
+/// parses `Name: value`
+pub fn parse_header(header: &str) -> Result<(String, String), HttpError> {
+    match header.find(':') {
+        Some(i) if i > 0 => Ok((header[..i].trim().to_string(), header[i + 1..].trim().to_string())),
+        _ => Err(HttpError::Header(header.to_string()))
+    }
+}
+
+fn connect(url: &Url, request: &Request) -> Result<Connection, HttpError> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("can't resolve {}", url.host));
+    let mut stream = None;
+    for addr in try!((url.host.as_str(), url.port).to_socket_addrs()) {
+        match TcpStream::connect_timeout(&addr, request.timeout) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_error = e
+        }
+    }
+    let stream = match stream {
+        Some(s) => s,
+        None => return Err(HttpError::Io(last_error))
+    };
+    try!(stream.set_read_timeout(Some(request.timeout)));
+    try!(stream.set_write_timeout(Some(request.timeout)));
+    if url.scheme == "http" {
+        return Ok(Connection::Plain(stream));
+    }
+    let mut builder = try!(SslConnector::builder(SslMethod::tls()));
+    if let Some(ref ca_file) = request.ca_file {
+        try!(builder.set_ca_file(ca_file));
+    }
+    if !request.tls_verify {
+        builder.set_verify(SslVerifyMode::NONE);
+    }
+    let config = try!(builder.build().configure()).verify_hostname(request.tls_verify);
+    match config.connect(&url.host, stream) {
+        Ok(s) => Ok(Connection::Tls(s)),
+        Err(e) => Err(HttpError::Tls(e.to_string()))
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, HttpError> {
+    let mut line = String::new();
+    try!(reader.read_line(&mut line));
+    Ok(line.trim_end_matches(|c| c == '\r' || c == '\n').to_string())
+}
+
+fn body_too_large() -> HttpError {
+    HttpError::Response(format!("body larger than {} bytes", MAX_BODY_SIZE))
+}
+
+/// appends `size` bytes to the body, the buffer grows with the data actually read
+fn read_body<R: Read>(reader: &mut R, size: usize, body: &mut Vec<u8>) -> Result<(), HttpError> {
+    if size > MAX_BODY_SIZE - body.len() {
+        return Err(body_too_large());
+    }
+    let read = try!(reader.take(size as u64).read_to_end(body));
+    if read < size {
+        return Err(HttpError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated body")));
+    }
+    Ok(())
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        let line = try!(read_line(reader));
+        let size_field = line.split(';').next().unwrap_or("").trim();
+        let size = match usize::from_str_radix(size_field, 16) {
+            Ok(s) => s,
+            Err(_) => return Err(HttpError::Response(format!("invalid chunk size {}", line)))
+        };
+        if size == 0 {
+            return Ok(body);
+        }
+        try!(read_body(reader, size, &mut body));
+        try!(read_line(reader));
+    }
+}
+
+/// reads a response, the body is delimited by Content-Length, chunks or the end of the connection,
+/// and limited to MAX_BODY_SIZE
+pub fn read_response<R: BufRead>(reader: &mut R, method: &str) -> Result<Response, HttpError> {
+    let status_line = try!(read_line(reader));
+    let status = match status_line.split_whitespace().nth(1).map(|s| s.parse::<i32>()) {
+        Some(Ok(s)) if status_line.starts_with("HTTP/") => s,
+        _ => return Err(HttpError::Response(format!("invalid status line {}", status_line)))
+    };
+    let mut headers = Vec::new();
+    loop {
+        let line = try!(read_line(reader));
+        if line.is_empty() {
+            break;
+        }
+        headers.push(try!(parse_header(&line)));
+    }
+    let mut response = Response {
+        status: status,
+        headers: headers,
+        body: String::new(),
+        time: Duration::from_secs(0)
+    };
+    if method == "HEAD" || status == 204 || status == 304 || (status >= 100 && status < 200) {
+        return Ok(response);
+    }
+    let chunked = response.header("Transfer-Encoding").map_or(false, |t| t.to_lowercase().contains("chunked"));
+    let body = if chunked {
+        try!(read_chunked(reader))
+    }
+    else if let Some(length) = response.header("Content-Length") {
+        let mut body = Vec::new();
+        try!(read_body(reader, try!(length.parse::<usize>()), &mut body));
+        body
+    }
+    else {
+        let mut body = Vec::new();
+        try!(reader.take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body));
+        if body.len() > MAX_BODY_SIZE {
+            return Err(body_too_large());
+        }
+        body
+    };
+    response.body = String::from_utf8_lossy(&body).into_owned();
+    Ok(response)
+}
+
+fn send(url: &Url, request: &Request, headers: &[(String, String)]) -> Result<Response, HttpError> {
+    let mut connection = try!(connect(url, request));
+    let default_port = if url.scheme == "https" { 443 } else { 80 };
+    let host = if url.host.contains(':') { format!("[{}]", url.host) } else { url.host.clone() };
+    let host = if url.port == default_port { host } else { format!("{}:{}", host, url.port) };
+    let mut message = format!("{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: lothaire\r\nAccept: */*\r\nConnection: close\r\n",
+                              request.method, url.path, host);
+    for &(ref name, ref value) in headers {
+        message.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    message.push_str("\r\n");
+    try!(connection.write_all(message.as_bytes()));
+    try!(connection.flush());
+    read_response(&mut io::BufReader::new(connection), &request.method)
+}
+
+/// a Location header is either absolute or relative to the host
+fn resolve_location(url: &Url, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let host = if url.host.contains(':') { format!("[{}]", url.host) } else { url.host.clone() };
+    let path = if location.starts_with('/') {
+        location.to_string()
+    }
+    else {
+        let base = &url.path[..url.path.rfind('/').map_or(0, |i| i + 1)];
+        format!("{}{}", base, location)
+    };
+    format!("{}://{}:{}{}", url.scheme, host, url.port, path)
+}
+
+/// same scheme, host and port
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme == b.scheme && a.host.to_lowercase() == b.host.to_lowercase() && a.port == b.port
+}
+
+/// sends the request, following up to max_redirects redirections. The request headers
+/// (`Authorization`...) are only sent to the origin of the url, not to other hosts.
+pub fn perform(request: &Request) -> Result<Response, HttpError> {
+    let start = Instant::now();
+    let origin = try!(parse_url(&request.url));
+    let mut url = try!(parse_url(&request.url));
+    let mut redirects = 0;
+    loop {
+        let headers: &[(String, String)] = if same_origin(&origin, &url) { &request.headers } else { &[] };
+        let mut response = try!(send(&url, request, headers));
+        let location = response.header("Location").map(|l| l.to_string());
+        match location {
+            Some(ref l) if response.status >= 300 && response.status < 400 && redirects < request.max_redirects => {
+                url = try!(parse_url(&resolve_location(&url, l)));
+                redirects += 1;
+            }
+            _ => {
+                response.time = start.elapsed();
+                return Ok(response);
+            }
+        }
+    }
+}
+
+/// `query=value`, the query of structured_file (`.checks[0].status`, `checks.0.status`)
+fn parse_json_setting(setting: &str) -> Result<(&str, &str, Vec<Step>), HttpError> {
+    let (query, value) = match setting.find('=') {
+        Some(i) => (&setting[..i], &setting[i + 1..]),
+        None => (setting, "")
+    };
+    Ok((query, value, try!(structured_file::parse_query(query))))
+}
+
+fn check_status(status: i32, response: &Response, result: &mut test::TestResult) {
+    let test_name = "http - status";
+    let condition = response.status == status;
+    test::update_test_result(condition, test_name, &status.to_string(), &response.status.to_string(),
+                             "incorrect status", result);
+}
+
+fn check_max_time(max_time: &Duration, response: &Response, result: &mut test::TestResult) {
+    let test_name = "http - max time";
+    let condition = response.time <= *max_time;
+    test::update_test_result(condition, test_name, &util::format_duration(max_time),
+                             &util::format_duration(&response.time), "response too slow", result);
+}
+
+/// a header without value only has to be present
+fn check_header(header: &str, response: &Response, result: &mut test::TestResult) {
+    let test_name = "http - header";
+    let (name, value) = match parse_header(header) {
+        Ok((n, v)) => (n, Some(v)),
+        Err(_) => (header.trim().to_string(), None)
+    };
+    let actual = response.header(&name);
+    let condition = match (actual, value.as_ref()) {
+        (Some(a), Some(v)) => a == v,
+        (Some(_), None) => true,
+        (None, _) => false
+    };
+    let actual = actual.map_or("missing".to_string(), |a| format!("{}: {}", name, a));
+    test::update_test_result(condition, test_name, header, &actual, "incorrect header", result);
+}
+
+fn check_body_contains(body_contains: &str, response: &Response, result: &mut test::TestResult) {
+    let test_name = "http - body contains";
+    let condition = response.body.contains(body_contains);
+    test::update_test_result(condition, test_name, body_contains, &response.body, "body doesn't contain text", result);
+}
+
+fn check_body_regex(body_regex: &Regex, response: &Response, result: &mut test::TestResult) {
+    let test_name = "http - body regex";
+    let condition = body_regex.is_match(&response.body);
+    test::update_test_result(condition, test_name, body_regex.as_str(), &response.body, "body doesn't match", result);
+}
+
+/// compared to the string value or the JSON encoding of other values, with wildcards every
+/// matched value is tested
+fn check_json(setting: &(&str, &str, Vec<Step>), body: &Result<Json, String>, result: &mut test::TestResult) {
+    let test_name = "http - json";
+    let &(query, value, ref steps) = setting;
+    let (condition, actual) = match *body {
+        Ok(ref json) => {
+            let values: Vec<String> = structured_file::evaluate(json, steps).into_iter()
+                .map(util::json_to_string)
+                .collect();
+            if values.is_empty() {
+                (false, "missing".to_string())
+            }
+            else {
+                (values.iter().all(|v| v == value), values.join(", "))
+            }
+        }
+        Err(ref e) => (false, format!("invalid json body: {}", e))
+    };
+    test::update_test_result(condition, test_name, &format!("{}={}", query, value), &format!("{}={}", query, actual),
+                             "incorrect json value", result);
+}
+
+pub fn check_http(request: &Request,
+                  status: Option<i32>,
+                  max_time: Option<Duration>,
+                  headers: &[&str],
+                  body_contains: Option<&str>,
+                  body_regex: Option<&Regex>,
+                  json: &[(&str, &str, Vec<Step>)]) -> test::TestResult {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let response = match perform(request) {
+        Ok(r) => r,
+        Err(e) => {
+            test::update_test_result(false, "http - request", &request.url, &format!("{:?}", e),
+                                     "request failed", &mut result);
+            return result;
+        }
+    };
+    status.map(|s| {
+        check_status(s, &response, &mut result);
+    });
+    max_time.map(|m| {
+        check_max_time(&m, &response, &mut result);
+    });
+    for header in headers {
+        check_header(header, &response, &mut result);
+    }
+    body_contains.map(|b| {
+        check_body_contains(b, &response, &mut result);
+    });
+    body_regex.map(|r| {
+        check_body_regex(r, &response, &mut result);
+    });
+    if !json.is_empty() {
+        let body = Json::from_str(&response.body).map_err(|e| e.to_string());
+        for setting in json {
+            check_json(setting, &body, &mut result);
+        }
+    }
+    result
+}
+
+pub fn check(options: &Options) -> Result<test::TestResult, HttpError> {
+    let mut parsed_headers = Vec::new();
+    for header in options.request_headers {
+        parsed_headers.push(try!(parse_header(header)));
+    }
+    try!(parse_url(options.url));
+    let request = Request {
+        url: options.url.to_string(),
+        method: options.method.unwrap_or("GET").to_uppercase(),
+        headers: parsed_headers,
+        timeout: try!(util::parse_duration(options.timeout)).unwrap_or(Duration::from_secs(10)),
+        max_redirects: try!(util::parse_int(options.max_redirects)).unwrap_or(0),
+        ca_file: options.ca_file.map(|c| c.to_string()),
+        tls_verify: try!(util::parse_bool(options.tls_verify)).unwrap_or(true)
+    };
+    let retries = try!(util::parse_int(options.retries)).unwrap_or(0);
+    let retry_delay = try!(util::parse_duration(options.retry_delay)).unwrap_or(Duration::from_secs(1));
+    let status = try!(util::parse_int(options.status));
+    let max_time = try!(util::parse_duration(options.max_time));
+    let body_regex = match options.body_regex {
+        Some(r) => Some(try!(Regex::new(r))),
+        None => None
+    };
+    let json = try!(options.json.iter().map(|j| parse_json_setting(j)).collect::<Result<Vec<_>, _>>());
+
+    let mut attempt = 0;
+    loop {
+        let result = check_http(&request, status, max_time, options.headers, options.body_contains,
+                                body_regex.as_ref(), &json);
+        if result.error == 0 || attempt >= retries {
+            return Ok(result);
+        }
+        attempt += 1;
+        thread::sleep(retry_delay);
+    }
+}
+
+// TESTS
+
+#[cfg(test)]
+use std::net::TcpListener;
+#[cfg(test)]
+use std::sync::mpsc;
+#[cfg(test)]
+use openssl::ssl::{SslAcceptor, SslFiletype};
+
+#[cfg(test)]
+const CA_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/http/ca.pem");
+#[cfg(test)]
+const SERVER_CERT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/http/server.pem");
+#[cfg(test)]
+const SERVER_KEY_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/http/server.key");
+
+/// serves the responses, one per connection, and sends back the requests received
+#[cfg(test)]
+fn serve(responses: Vec<&'static str>, tls: bool) -> (u16, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut connection = if tls {
+                let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+                acceptor.set_private_key_file(SERVER_KEY_FIXTURE, SslFiletype::PEM).unwrap();
+                acceptor.set_certificate_chain_file(SERVER_CERT_FIXTURE).unwrap();
+                match acceptor.build().accept(stream) {
+                    Ok(s) => Connection::Tls(s),
+                    Err(_) => continue
+                }
+            }
+            else {
+                Connection::Plain(stream)
+            };
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = connection.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let _ = sender.send(String::from_utf8_lossy(&request).into_owned());
+            let _ = connection.write_all(response.replace("{port}", &port.to_string()).as_bytes());
+        }
+    });
+    (port, receiver)
+}
+
+#[cfg(test)]
+const JSON_RESPONSE: &'static str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 54\r\n\r\n\
+                                     {\"status\":\"ok\",\"checks\":[{\"name\":\"db\",\"latency\":12}]}\n";
+
+#[test]
+fn parse_url_test_success() {
+    assert_eq!(parse_url("http://localhost:8080/health").unwrap(),
+               Url { scheme: "http".to_string(), host: "localhost".to_string(), port: 8080, path: "/health".to_string() });
+    assert_eq!(parse_url("https://example.com").unwrap(),
+               Url { scheme: "https".to_string(), host: "example.com".to_string(), port: 443, path: "/".to_string() });
+    assert_eq!(parse_url("http://[::1]:81/a?b=c").unwrap(),
+               Url { scheme: "http".to_string(), host: "::1".to_string(), port: 81, path: "/a?b=c".to_string() });
+    assert_eq!(parse_url("http://[::1]/").unwrap().port, 80);
+    assert!(parse_url("ftp://example.com").is_err());
+    assert!(parse_url("localhost:8080").is_err());
+    assert!(parse_url("http://localhost:http/").is_err());
+}
+
+#[test]
+fn read_response_test_success() {
+    let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\n\r\n";
+    let response = read_response(&mut io::BufReader::new(raw.as_bytes()), "GET").unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("transfer-encoding"), Some("chunked"));
+    assert_eq!(response.body, "hello world");
+    let raw = "HTTP/1.0 404 Not Found\r\nServer: test\r\n\r\nnot found";
+    let response = read_response(&mut io::BufReader::new(raw.as_bytes()), "GET").unwrap();
+    assert_eq!(response.status, 404);
+    assert_eq!(response.body, "not found");
+    assert!(read_response(&mut io::BufReader::new("garbage\r\n\r\n".as_bytes()), "GET").is_err());
+}
+
+#[test]
+fn check_json_test_success() {
+    let body = Ok(Json::from_str("{\"a\":{\"b\":[1,{\"c\":\"d\"}]},\"e\":true}").unwrap());
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    for setting in &["a.b.1.c=d", ".a.b[1].c=d", "a.b.0=1", "e=true", ".a.b[*].c=d", "a.b.2=", "e.f="] {
+        check_json(&parse_json_setting(setting).unwrap(), &body, &mut result);
+    }
+    assert_eq!(result.success, 5);
+    assert_eq!(result.error, 2);
+    match result.summary[6] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "e.f=");
+            assert_eq!(e.actual, "e.f=missing");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(parse_json_setting(".a..b=1").is_err());
+}
+
+#[test]
+fn read_response_test_too_large() {
+    let raw = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\nhello", usize::max_value());
+    assert!(read_response(&mut io::BufReader::new(raw.as_bytes()), "GET").is_err());
+    let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffff\r\nhello\r\n0\r\n\r\n";
+    assert!(read_response(&mut io::BufReader::new(raw.as_bytes()), "GET").is_err());
+    let raw = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello";
+    assert!(read_response(&mut io::BufReader::new(raw.as_bytes()), "GET").is_err());
+}
+
+#[test]
+fn check_test_success() {
+    let (port, requests) = serve(vec![JSON_RESPONSE], false);
+    let url = format!("http://localhost:{}/health", port);
+    let result = check(&Options {
+        url: &url,
+        request_headers: &["X-Token: secret"],
+        timeout: Some("2"),
+        status: Some("200"),
+        max_time: Some("2"),
+        headers: &["Content-Type: application/json", "content-length"],
+        body_contains: Some("\"ok\""),
+        body_regex: Some("latency\":[0-9]+"),
+        json: &["status=ok", "checks.0.latency=12"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 8);
+    assert_eq!(result.error, 0);
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("GET /health HTTP/1.1\r\n"));
+    assert!(request.contains(&format!("Host: localhost:{}\r\n", port)));
+    assert!(request.contains("X-Token: secret\r\n"));
+}
+
+#[test]
+fn check_test_error() {
+    let (port, _) = serve(vec![JSON_RESPONSE], false);
+    let url = format!("http://127.0.0.1:{}/health", port);
+    let result = check(&Options {
+        url: &url,
+        status: Some("204"),
+        headers: &["Content-Type: text/html", "X-Missing"],
+        json: &["status=ko", "missing"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 5);
+    match result.summary[4] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "missing=");
+            assert_eq!(e.actual, "missing=missing");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check(&Options { url: &url, timeout: Some("1"), status: Some("200"), ..Options::default() }).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check(&Options { url: &url, request_headers: &["Invalid"], ..Options::default() }).is_err());
+}
+
+#[test]
+fn check_test_redirect() {
+    let (port, _) = serve(vec!["HTTP/1.1 301 Moved\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n",
+                               "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{port}/final\r\nContent-Length: 0\r\n\r\n",
+                               JSON_RESPONSE,
+                               "HTTP/1.1 301 Moved\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n"], false);
+    let url = format!("http://127.0.0.1:{}/", port);
+    let result = check(&Options {
+        url: &url,
+        max_redirects: Some("2"),
+        status: Some("200"),
+        json: &["status=ok"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check(&Options {
+        url: &url,
+        status: Some("301"),
+        headers: &["Location: /new"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_test_redirect_headers() {
+    let (port, requests) = serve(vec!["HTTP/1.1 301 Moved\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n",
+                                      "HTTP/1.1 302 Found\r\nLocation: http://localhost:{port}/final\r\n\
+                                       Content-Length: 0\r\n\r\n",
+                                      JSON_RESPONSE], false);
+    let url = format!("http://127.0.0.1:{}/", port);
+    let result = check(&Options {
+        url: &url,
+        request_headers: &["Authorization: Bearer secret"],
+        max_redirects: Some("2"),
+        status: Some("200"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 1);
+    assert!(requests.recv().unwrap().contains("Authorization: Bearer secret\r\n"));
+    assert!(requests.recv().unwrap().contains("Authorization: Bearer secret\r\n"));
+    let request = requests.recv().unwrap();
+    assert!(request.starts_with("GET /final HTTP/1.1\r\n"));
+    assert!(!request.contains("Authorization"));
+}
+
+#[test]
+fn check_test_retries() {
+    let (port, _) = serve(vec!["HTTP/1.1 503 Unavailable\r\nContent-Length: 0\r\n\r\n", JSON_RESPONSE], false);
+    let url = format!("http://127.0.0.1:{}/", port);
+    let result = check(&Options {
+        url: &url,
+        retries: Some("2"),
+        retry_delay: Some("0.01"),
+        status: Some("200"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_test_tls() {
+    let (port, _) = serve(vec![JSON_RESPONSE, JSON_RESPONSE, JSON_RESPONSE], true);
+    let url = format!("https://localhost:{}/health", port);
+    let result = check(&Options {
+        url: &url,
+        ca_file: Some(CA_FIXTURE),
+        status: Some("200"),
+        json: &["status=ok"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check(&Options { url: &url, status: Some("200"), ..Options::default() }).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.test, "http - request"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check(&Options {
+        url: &url,
+        tls_verify: Some("false"),
+        status: Some("200"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 1);
+}
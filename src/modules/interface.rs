@@ -0,0 +1,317 @@
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::num;
+use std::ptr;
+use std::str;
+use libc;
+use modules::test;
+use util;
+
+const SYS_CLASS_NET: &'static str = "/sys/class/net";
+
+#[derive(Debug)]
+pub enum InterfaceError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    ParseBool(str::ParseBoolError),
+    Address(String)
+}
+
+impl From<io::Error> for InterfaceError {
+    fn from(err: io::Error) -> InterfaceError {
+        InterfaceError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for InterfaceError {
+    fn from(err: num::ParseIntError) -> InterfaceError {
+        InterfaceError::ParseInt(err)
+    }
+}
+
+impl From<str::ParseBoolError> for InterfaceError {
+    fn from(err: str::ParseBoolError) -> InterfaceError {
+        InterfaceError::ParseBool(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Interface {
+    pub name: String,
+    pub state: String,
+    pub mtu: i32,
+    pub mac: String
+}
+
+fn read_attribute(sys_class_net: &str, name: &str, attribute: &str) -> Result<String, io::Error> {
+    let mut file = try!(fs::File::open(format!("{}/{}/{}", sys_class_net, name, attribute)));
+    let mut content = String::new();
+    try!(file.read_to_string(&mut content));
+    Ok(content.trim().to_string())
+}
+
+/// returns the interface described in /sys/class/net, None if it doesn't exist
+pub fn get_interface(sys_class_net: &str, name: &str) -> Result<Option<Interface>, InterfaceError> {
+    if fs::metadata(format!("{}/{}", sys_class_net, name)).is_err() {
+        return Ok(None);
+    }
+    let mtu = try!(try!(read_attribute(sys_class_net, name, "mtu")).parse::<i32>());
+    Ok(Some(Interface {
+        name: name.to_string(),
+        state: try!(read_attribute(sys_class_net, name, "operstate")),
+        mtu: mtu,
+        mac: try!(read_attribute(sys_class_net, name, "address"))
+    }))
+}
+
+fn get_sockaddr_ip(addr: *const libc::sockaddr) -> Option<IpAddr> {
+    if addr.is_null() {
+        return None;
+    }
+    unsafe {
+        match (*addr).sa_family as i32 {
+            libc::AF_INET => {
+                let addr = &*(addr as *const libc::sockaddr_in);
+                Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr))))
+            },
+            libc::AF_INET6 => {
+                let addr = &*(addr as *const libc::sockaddr_in6);
+                Some(IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr)))
+            },
+            _ => None
+        }
+    }
+}
+
+/// returns the number of leading ones of a netmask
+pub fn get_prefix_len(netmask: &IpAddr) -> u32 {
+    match *netmask {
+        IpAddr::V4(ref m) => m.octets().iter().map(|o| o.count_ones()).sum(),
+        IpAddr::V6(ref m) => m.octets().iter().map(|o| o.count_ones()).sum()
+    }
+}
+
+/// returns the addresses of an interface with their prefix, like `10.0.0.5/24`
+pub fn get_addresses(name: &str) -> Result<Vec<String>, io::Error> {
+    let mut ifaddrs: *mut libc::ifaddrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut addresses = Vec::new();
+    let mut current = ifaddrs;
+    while !current.is_null() {
+        let ifaddr = unsafe { &*current };
+        let ifa_name = unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_string_lossy().into_owned();
+        if ifa_name == name {
+            if let Some(ip) = get_sockaddr_ip(ifaddr.ifa_addr) {
+                let prefix = match get_sockaddr_ip(ifaddr.ifa_netmask) {
+                    Some(netmask) => get_prefix_len(&netmask),
+                    None => if ip.is_ipv4() { 32 } else { 128 }
+                };
+                addresses.push(format!("{}/{}", ip, prefix));
+            }
+        }
+        current = ifaddr.ifa_next;
+    }
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    Ok(addresses)
+}
+
+/// `10.0.0.5/24` or `fe80::1`, the prefix length is optional
+fn parse_address(address: &str) -> Option<(IpAddr, Option<u8>)> {
+    let mut parts = address.splitn(2, '/');
+    let ip = match parts.next().and_then(|a| a.trim().parse::<IpAddr>().ok()) {
+        Some(ip) => ip,
+        None => return None
+    };
+    let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+    match parts.next() {
+        None => Some((ip, None)),
+        Some(p) => match p.trim().parse::<u8>() {
+            Ok(prefix) if prefix <= max_prefix => Some((ip, Some(prefix))),
+            _ => None
+        }
+    }
+}
+
+/// addresses are compared parsed, so `FE80::1` matches `fe80::1`. An expected address without
+/// prefix matches the address with any prefix.
+fn address_matches(expected: &(IpAddr, Option<u8>), actual: &(IpAddr, Option<u8>)) -> bool {
+    expected.0 == actual.0 && expected.1.map_or(true, |p| Some(p) == actual.1)
+}
+
+fn check_addresses(expected: &str,
+                   exact: bool,
+                   actual: &Vec<String>,
+                   result: &mut test::TestResult) -> Result<(), InterfaceError> {
+    let test_name = if exact { "interface - addresses" } else { "interface - contains addresses" };
+    let mut expected_list = Vec::new();
+    for address in expected.split(',').filter(|a| !a.is_empty()) {
+        expected_list.push(try!(parse_address(address).ok_or_else(|| InterfaceError::Address(address.to_string()))));
+    }
+    let actual_list: Vec<(IpAddr, Option<u8>)> = actual.iter().filter_map(|a| parse_address(a)).collect();
+    let all_found = expected_list.iter().all(|e| actual_list.iter().any(|a| address_matches(e, a)));
+    let no_extra = actual_list.iter().all(|a| expected_list.iter().any(|e| address_matches(e, a)));
+    let condition = all_found && (!exact || no_extra);
+    let message = if exact { "incorrect addresses" } else { "missing addresses" };
+    test::update_test_result(condition, test_name, expected, &actual.join(","), message, result);
+    Ok(())
+}
+
+fn check_state(state: &str, interface: &Interface, result: &mut test::TestResult) {
+    let test_name = "interface - state";
+    let condition = interface.state == state;
+    test::update_test_result(condition, test_name, state, &interface.state, "incorrect link state", result);
+}
+
+fn check_mtu(mtu: i32, interface: &Interface, result: &mut test::TestResult) {
+    let test_name = "interface - mtu";
+    let condition = interface.mtu == mtu;
+    test::update_test_result(condition, test_name, &mtu.to_string(), &interface.mtu.to_string(), "incorrect mtu", result);
+}
+
+fn check_mac(mac: &str, interface: &Interface, result: &mut test::TestResult) {
+    let test_name = "interface - mac";
+    let condition = interface.mac == mac.to_lowercase();
+    test::update_test_result(condition, test_name, mac, &interface.mac, "incorrect mac address", result);
+}
+
+pub fn check_interface(sys_class_net: &str,
+                       name: &str,
+                       exists: &str,
+                       state: Option<&str>,
+                       mtu: Option<&str>,
+                       mac: Option<&str>,
+                       addresses: Option<&str>,
+                       contains_addresses: Option<&str>) -> Result<test::TestResult, InterfaceError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists_bool: bool = try!(exists.parse());
+    let interface_result = try!(get_interface(sys_class_net, name));
+    test::check_exists(&interface_result, exists_bool, &mut result, "interface - exists".to_string());
+    match interface_result {
+        None => Ok(result),
+        Some(interface) => {
+            state.map(|s| {
+                check_state(s, &interface, &mut result);
+            });
+            let mtu = try!(util::parse_int(mtu));
+            mtu.map(|m| {
+                check_mtu(m, &interface, &mut result);
+            });
+            mac.map(|m| {
+                check_mac(m, &interface, &mut result);
+            });
+            if addresses.is_some() || contains_addresses.is_some() {
+                let actual = try!(get_addresses(name));
+                if let Some(a) = addresses {
+                    try!(check_addresses(a, true, &actual, &mut result));
+                }
+                if let Some(a) = contains_addresses {
+                    try!(check_addresses(a, false, &actual, &mut result));
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+pub fn check(name: &str,
+             exists: &str,
+             state: Option<&str>,
+             mtu: Option<&str>,
+             mac: Option<&str>,
+             addresses: Option<&str>,
+             contains_addresses: Option<&str>) -> Result<test::TestResult, InterfaceError> {
+    check_interface(SYS_CLASS_NET, name, exists, state, mtu, mac, addresses, contains_addresses)
+}
+
+// TESTS
+
+#[cfg(test)]
+const SYS_CLASS_NET_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/interface/sys/class/net");
+
+#[test]
+fn get_interface_test_success() {
+    let interface = get_interface(SYS_CLASS_NET_FIXTURE, "eth0").unwrap().unwrap();
+    assert_eq!(interface.state, "up");
+    assert_eq!(interface.mtu, 1500);
+    assert_eq!(interface.mac, "52:54:00:12:34:56");
+    assert!(get_interface(SYS_CLASS_NET_FIXTURE, "eth9").unwrap().is_none());
+}
+
+#[test]
+fn get_prefix_len_test_success() {
+    assert_eq!(get_prefix_len(&"255.255.255.0".parse::<IpAddr>().unwrap()), 24);
+    assert_eq!(get_prefix_len(&"255.0.0.0".parse::<IpAddr>().unwrap()), 8);
+    assert_eq!(get_prefix_len(&"ffff:ffff:ffff:ffff::".parse::<IpAddr>().unwrap()), 64);
+}
+
+#[test]
+fn get_addresses_test_success() {
+    let addresses = get_addresses("lo").unwrap();
+    assert!(addresses.contains(&"127.0.0.1/8".to_string()));
+    assert!(get_addresses("notexists").unwrap().is_empty());
+}
+
+#[test]
+fn check_addresses_test_success() {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let actual = vec!["10.0.0.5/24".to_string(), "10.0.0.6/24".to_string(), "fe80::1/64".to_string(),
+                      "2001:db8::1/64".to_string()];
+    check_addresses("10.0.0.5/24,10.0.0.6", false, &actual, &mut result).unwrap();
+    check_addresses("10.0.0.5,10.0.0.6/24,fe80::1,2001:db8::1", true, &actual, &mut result).unwrap();
+    check_addresses("FE80::1/64,2001:db8:0::1/064", false, &actual, &mut result).unwrap();
+    assert_eq!(result.success, 3);
+    check_addresses("10.0.0.5/24,10.0.0.6", true, &actual, &mut result).unwrap();
+    check_addresses("10.0.0.7", false, &actual, &mut result).unwrap();
+    check_addresses("10.0.0.5/16", false, &actual, &mut result).unwrap();
+    assert_eq!(result.error, 3);
+    match result.summary[3] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "10.0.0.5/24,10.0.0.6");
+            assert_eq!(e.actual, "10.0.0.5/24,10.0.0.6/24,fe80::1/64,2001:db8::1/64");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_addresses("10.0.0.300", false, &actual, &mut result).is_err());
+    assert!(check_addresses("10.0.0.5/33", false, &actual, &mut result).is_err());
+    assert!(check_addresses("fe80::1/abc", false, &actual, &mut result).is_err());
+}
+
+#[test]
+fn check_interface_test_success() {
+    let result = check_interface(SYS_CLASS_NET_FIXTURE, "eth0", "true", Some("up"), Some("1500"),
+                                 Some("52:54:00:12:34:56"), None, None).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    let result = check_interface(SYS_CLASS_NET_FIXTURE, "eth9", "false", Some("up"), None, None, None, None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_interface_test_error() {
+    let result = check_interface(SYS_CLASS_NET_FIXTURE, "bond0", "true", Some("up"), Some("1500"),
+                                 Some("52:54:00:AB:CD:EF"), None, None).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 2);
+    match result.summary[2] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "1500");
+            assert_eq!(e.actual, "9000");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_interface(SYS_CLASS_NET_FIXTURE, "bond0", "true", None, Some("big"), None, None, None).is_err());
+}
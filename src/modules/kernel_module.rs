@@ -0,0 +1,195 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::str;
+use modules::test;
+use util;
+
+const PROC_MODULES: &'static str = "/proc/modules";
+const MODPROBE_DIR: &'static str = "/etc/modprobe.d";
+
+/// commands which make `install <module> <command>` a no-op
+const DISABLE_COMMANDS: [&'static str; 4] = ["/bin/true", "/bin/false", "/usr/bin/true", "/usr/bin/false"];
+
+#[derive(Debug)]
+pub enum KernelModuleError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError)
+}
+
+impl From<io::Error> for KernelModuleError {
+    fn from(err: io::Error) -> KernelModuleError {
+        KernelModuleError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for KernelModuleError {
+    fn from(err: str::ParseBoolError) -> KernelModuleError {
+        KernelModuleError::ParseBool(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct ModprobeConfig {
+    pub blacklisted: Vec<String>,
+    pub disabled: Vec<String>
+}
+
+/// modprobe treats `-` and `_` as the same character in module names
+pub fn normalize_name(name: &str) -> String {
+    name.replace("-", "_")
+}
+
+/// returns the names of the modules listed in /proc/modules
+pub fn get_loaded_modules(path: &str) -> Result<Vec<String>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut modules = Vec::new();
+    for l in reader.lines() {
+        let line = try!(l);
+        if let Some(name) = line.split_whitespace().next() {
+            modules.push(normalize_name(name));
+        }
+    }
+    Ok(modules)
+}
+
+fn parse_modprobe_file(path: &str, config: &mut ModprobeConfig) -> Result<(), io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    for l in reader.lines() {
+        let line = try!(l);
+        let line = line.split('#').next().unwrap_or("");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.len() {
+            0 | 1 => {},
+            2 if fields[0] == "blacklist" => config.blacklisted.push(normalize_name(fields[1])),
+            _ if fields[0] == "install" => {
+                if fields.len() == 3 && DISABLE_COMMANDS.contains(&fields[2]) {
+                    config.disabled.push(normalize_name(fields[1]));
+                }
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// returns the blacklisted and disabled (`install <module> /bin/true`) modules
+/// of the *.conf files in a modprobe.d directory
+pub fn get_modprobe_config(dir: &str) -> Result<ModprobeConfig, io::Error> {
+    let mut config = ModprobeConfig {
+        blacklisted: Vec::new(),
+        disabled: Vec::new()
+    };
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries {
+            let path = try!(entry).path();
+            if path.extension().map(|e| e == "conf").unwrap_or(false) {
+                files.push(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+    files.sort();
+    for file in files {
+        try!(parse_modprobe_file(&file, &mut config));
+    }
+    Ok(config)
+}
+
+fn check_state(test_name: &str, expected: bool, actual: bool, result: &mut test::TestResult) {
+    let message = format!("{} should be {}", test_name, expected);
+    test::update_test_result(expected == actual, test_name, &expected.to_string(), &actual.to_string(), &message, result);
+}
+
+pub fn check_module(proc_modules: &str,
+                    modprobe_dir: &str,
+                    name: &str,
+                    loaded: Option<&str>,
+                    blacklisted: Option<&str>,
+                    disabled: Option<&str>) -> Result<test::TestResult, KernelModuleError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let name = normalize_name(name);
+    let loaded = try!(util::parse_bool(loaded));
+    let blacklisted = try!(util::parse_bool(blacklisted));
+    let disabled = try!(util::parse_bool(disabled));
+    if let Some(loaded) = loaded {
+        let modules = try!(get_loaded_modules(proc_modules));
+        check_state("kernel_module - loaded", loaded, modules.contains(&name), &mut result);
+    }
+    if blacklisted.is_some() || disabled.is_some() {
+        let config = try!(get_modprobe_config(modprobe_dir));
+        if let Some(blacklisted) = blacklisted {
+            check_state("kernel_module - blacklisted", blacklisted, config.blacklisted.contains(&name), &mut result);
+        }
+        if let Some(disabled) = disabled {
+            check_state("kernel_module - disabled", disabled, config.disabled.contains(&name), &mut result);
+        }
+    }
+    Ok(result)
+}
+
+pub fn check(name: &str,
+             loaded: Option<&str>,
+             blacklisted: Option<&str>,
+             disabled: Option<&str>) -> Result<test::TestResult, KernelModuleError> {
+    check_module(PROC_MODULES, MODPROBE_DIR, name, loaded, blacklisted, disabled)
+}
+
+// TESTS
+
+#[cfg(test)]
+const PROC_MODULES_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/kernel_module/modules");
+#[cfg(test)]
+const MODPROBE_DIR_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/kernel_module/modprobe.d");
+
+#[test]
+fn get_loaded_modules_test_success() {
+    let modules = get_loaded_modules(PROC_MODULES_FIXTURE).unwrap();
+    assert_eq!(modules, vec!["nf_conntrack", "usb_storage", "ext4", "dccp"]);
+    assert!(get_loaded_modules("/notexists").is_err());
+}
+
+#[test]
+fn get_modprobe_config_test_success() {
+    let config = get_modprobe_config(MODPROBE_DIR_FIXTURE).unwrap();
+    assert_eq!(config.blacklisted, vec!["cramfs", "pcspkr", "usb_storage"]);
+    assert_eq!(config.disabled, vec!["cramfs", "usb_storage", "dccp"]);
+}
+
+#[test]
+fn check_module_test_success() {
+    let result = check_module(PROC_MODULES_FIXTURE, MODPROBE_DIR_FIXTURE, "cramfs",
+                              Some("false"), Some("true"), Some("true")).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check_module(PROC_MODULES_FIXTURE, MODPROBE_DIR_FIXTURE, "ext4",
+                              Some("true"), Some("false"), None).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_module_test_error() {
+    let result = check_module(PROC_MODULES_FIXTURE, MODPROBE_DIR_FIXTURE, "usb-storage",
+                              Some("false"), Some("true"), Some("true")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "kernel_module - loaded");
+            assert_eq!(e.expected, "false");
+            assert_eq!(e.actual, "true");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_module(PROC_MODULES_FIXTURE, MODPROBE_DIR_FIXTURE, "dccp",
+                              None, Some("true"), None).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check_module(PROC_MODULES_FIXTURE, MODPROBE_DIR_FIXTURE, "dccp", Some("hello"), None, None).is_err());
+}
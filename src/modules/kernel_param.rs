@@ -0,0 +1,255 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str;
+use modules::test;
+use util;
+
+const PROC_SYS: &'static str = "/proc/sys";
+const SYSCTL_CONF: &'static str = "/etc/sysctl.conf";
+const SYSCTL_DIR: &'static str = "/etc/sysctl.d";
+
+#[derive(Debug)]
+pub enum KernelParamError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError)
+}
+
+impl From<io::Error> for KernelParamError {
+    fn from(err: io::Error) -> KernelParamError {
+        KernelParamError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for KernelParamError {
+    fn from(err: str::ParseBoolError) -> KernelParamError {
+        KernelParamError::ParseBool(err)
+    }
+}
+
+fn swap_separators(name: &str) -> String {
+    name.chars().map(|c| match c {
+        '.' => '/',
+        '/' => '.',
+        c => c
+    }).collect()
+}
+
+/// `net/ipv4/ip_forward` and `net.ipv4.ip_forward` are both valid sysctl names. Like sysctl,
+/// dots and slashes are swapped when the first separator is a slash, so the dot of a VLAN
+/// interface is kept: `net/ipv4/conf/eth0.100/rp_filter` is `net.ipv4.conf.eth0/100.rp_filter`
+pub fn normalize_name(name: &str) -> String {
+    let name = name.trim();
+    match name.find(|c| c == '.' || c == '/') {
+        Some(i) if name[i..].starts_with('/') => swap_separators(name),
+        _ => name.to_string()
+    }
+}
+
+/// multi-value parameters are tab separated in /proc/sys, space separated in configs
+pub fn normalize_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// returns the /proc/sys path of a sysctl name
+pub fn get_param_path(proc_sys: &str, name: &str) -> String {
+    format!("{}/{}", proc_sys, swap_separators(&normalize_name(name)))
+}
+
+/// returns the runtime value of a kernel parameter, None if the parameter doesn't exist
+pub fn get_runtime_value(proc_sys: &str, name: &str) -> Result<Option<String>, io::Error> {
+    let mut file = match fs::File::open(get_param_path(proc_sys, name)) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e)
+    };
+    let mut content = String::new();
+    try!(file.read_to_string(&mut content));
+    Ok(Some(normalize_value(&content)))
+}
+
+/// returns the value set for `name` in a sysctl configuration file, if any
+fn get_file_value(path: &Path, name: &str) -> Result<Option<String>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut value = None;
+    for l in reader.lines() {
+        let line = try!(l);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let line = line.trim_start_matches('-');
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        if let Some(v) = parts.next() {
+            if normalize_name(key) == name {
+                value = Some(normalize_value(v));
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// returns the value applied at boot for `name`, following sysctl --system order:
+/// *.conf files of the sysctl.d directory in lexical order, then sysctl.conf
+pub fn get_persisted_value(sysctl_conf: &str, sysctl_dir: &str, name: &str) -> Result<Option<String>, io::Error> {
+    let name = normalize_name(name);
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(sysctl_dir) {
+        for entry in entries {
+            let path = try!(entry).path();
+            if path.extension().map(|e| e == "conf").unwrap_or(false) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    if Path::new(sysctl_conf).exists() {
+        files.push(Path::new(sysctl_conf).to_path_buf());
+    }
+    let mut value = None;
+    for file in files {
+        if let Some(v) = try!(get_file_value(&file, &name)) {
+            value = Some(v);
+        }
+    }
+    Ok(value)
+}
+
+fn check_value(value: &str, runtime: &Option<String>, result: &mut test::TestResult) {
+    let test_name = "kernel_param - value";
+    let expected = normalize_value(value);
+    let (condition, actual) = match *runtime {
+        Some(ref v) => (*v == expected, v.to_string()),
+        None => (false, "parameter not found".to_string())
+    };
+    test::update_test_result(condition, test_name, &expected, &actual, "incorrect runtime value", result);
+}
+
+fn check_persisted(persisted: &Option<String>, runtime: &Option<String>, result: &mut test::TestResult) {
+    let test_name = "kernel_param - persisted";
+    let expected = match *runtime {
+        Some(ref v) => v.to_string(),
+        None => "parameter not found".to_string()
+    };
+    let (condition, actual) = match *persisted {
+        Some(ref v) => (runtime.is_some() && *v == expected, v.to_string()),
+        None => (false, "not persisted".to_string())
+    };
+    test::update_test_result(condition, test_name, &expected, &actual,
+                             "persisted value differs from runtime value", result);
+}
+
+pub fn check_param(proc_sys: &str,
+                   sysctl_conf: &str,
+                   sysctl_dir: &str,
+                   name: &str,
+                   value: Option<&str>,
+                   persisted: Option<&str>) -> Result<test::TestResult, KernelParamError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let persisted = try!(util::parse_bool(persisted)).unwrap_or(false);
+    let runtime = try!(get_runtime_value(proc_sys, name));
+    value.map(|v| {
+        check_value(v, &runtime, &mut result);
+    });
+    if persisted {
+        let persisted_value = try!(get_persisted_value(sysctl_conf, sysctl_dir, name));
+        check_persisted(&persisted_value, &runtime, &mut result);
+    }
+    Ok(result)
+}
+
+pub fn check(name: &str, value: Option<&str>, persisted: Option<&str>) -> Result<test::TestResult, KernelParamError> {
+    check_param(PROC_SYS, SYSCTL_CONF, SYSCTL_DIR, name, value, persisted)
+}
+
+// TESTS
+
+#[cfg(test)]
+const PROC_SYS_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/kernel_param/proc/sys");
+#[cfg(test)]
+const SYSCTL_CONF_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/kernel_param/sysctl.conf");
+#[cfg(test)]
+const SYSCTL_DIR_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/kernel_param/sysctl.d");
+
+#[test]
+fn get_param_path_test_success() {
+    assert_eq!(get_param_path("/proc/sys", "net.ipv4.ip_forward"), "/proc/sys/net/ipv4/ip_forward");
+    assert_eq!(get_param_path("/proc/sys", "kernel/randomize_va_space"), "/proc/sys/kernel/randomize_va_space");
+    assert_eq!(get_param_path("/proc/sys", "net.ipv4.conf.eth0/100.rp_filter"),
+               "/proc/sys/net/ipv4/conf/eth0.100/rp_filter");
+    assert_eq!(get_param_path("/proc/sys", "net/ipv4/conf/eth0.100/rp_filter"),
+               "/proc/sys/net/ipv4/conf/eth0.100/rp_filter");
+}
+
+#[test]
+fn normalize_name_test_success() {
+    assert_eq!(normalize_name(" net/ipv4/ip_forward "), "net.ipv4.ip_forward");
+    assert_eq!(normalize_name("net/ipv4/conf/eth0.100/rp_filter"), "net.ipv4.conf.eth0/100.rp_filter");
+    assert_eq!(normalize_name("net.ipv4.conf.eth0/100.rp_filter"), "net.ipv4.conf.eth0/100.rp_filter");
+}
+
+#[test]
+fn get_runtime_value_test_success() {
+    let value = get_runtime_value(PROC_SYS_FIXTURE, "net.ipv4.ip_local_port_range").unwrap();
+    assert_eq!(value, Some("32768 60999".to_string()));
+    let value = get_runtime_value(PROC_SYS_FIXTURE, "kernel.randomize_va_space").unwrap();
+    assert_eq!(value, Some("2".to_string()));
+    let value = get_runtime_value(PROC_SYS_FIXTURE, "kernel.notexists").unwrap();
+    assert!(value.is_none());
+}
+
+#[test]
+fn get_persisted_value_test_success() {
+    // sysctl.conf is applied after sysctl.d
+    let value = get_persisted_value(SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE, "net.ipv4.ip_forward").unwrap();
+    assert_eq!(value, Some("1".to_string()));
+    let value = get_persisted_value(SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE, "net.ipv4.ip_local_port_range").unwrap();
+    assert_eq!(value, Some("32768 60999".to_string()));
+    // README is not a .conf file
+    let value = get_persisted_value(SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE, "kernel.sysrq").unwrap();
+    assert_eq!(value, Some("0".to_string()));
+    let value = get_persisted_value(SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE, "vm.swappiness").unwrap();
+    assert!(value.is_none());
+}
+
+#[test]
+fn check_param_test_success() {
+    let result = check_param(PROC_SYS_FIXTURE, SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE,
+                             "kernel.randomize_va_space", Some("2"), Some("true")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check_param(PROC_SYS_FIXTURE, SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE,
+                             "net.ipv4.ip_local_port_range", Some("32768  60999"), Some("true")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_param_test_error() {
+    let result = check_param(PROC_SYS_FIXTURE, SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE,
+                             "net.ipv4.ip_forward", Some("0"), Some("true")).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "0");
+            assert_eq!(e.actual, "1");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_param(PROC_SYS_FIXTURE, SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE,
+                             "kernel.notexists", Some("0"), None).unwrap();
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "parameter not found"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_param(PROC_SYS_FIXTURE, SYSCTL_CONF_FIXTURE, SYSCTL_DIR_FIXTURE,
+                        "kernel.sysrq", None, Some("hello")).is_err());
+}
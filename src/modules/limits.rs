@@ -0,0 +1,321 @@
+use std::fs;
+use std::i64;
+use std::io;
+use std::io::prelude::*;
+use modules::test;
+use modules::user;
+use util;
+
+const LIMITS_CONF: &'static str = "etc/security/limits.conf";
+const LIMITS_D: &'static str = "etc/security/limits.d";
+
+#[derive(Debug)]
+pub enum LimitsError {
+    Io(io::Error),
+    User(user::UserError),
+    UnknownUser(String),
+    Setting(String)
+}
+
+impl From<io::Error> for LimitsError {
+    fn from(err: io::Error) -> LimitsError {
+        LimitsError::Io(err)
+    }
+}
+
+impl From<user::UserError> for LimitsError {
+    fn from(err: user::UserError) -> LimitsError {
+        LimitsError::User(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Limit {
+    pub domain: String,
+    /// soft, hard or `-` for both
+    pub limit_type: String,
+    pub item: String,
+    pub value: String,
+    pub file: String,
+    pub line: usize
+}
+
+impl Limit {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+fn parse_file(path: &str, limits: &mut Vec<Limit>) -> Result<(), io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let line = line.split('#').next().unwrap_or("");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        limits.push(Limit {
+            domain: fields[0].to_string(),
+            limit_type: fields[1].to_string(),
+            item: fields[2].to_string(),
+            value: fields[3].to_string(),
+            file: path.to_string(),
+            line: i + 1
+        });
+    }
+    Ok(())
+}
+
+/// limits.conf, then the `*.conf` files of limits.d sorted by name, like pam_limits
+pub fn get_limits(root: &str) -> Result<Vec<Limit>, io::Error> {
+    let mut limits = Vec::new();
+    let limits_conf = util::join_root(root, LIMITS_CONF);
+    if fs::metadata(&limits_conf).is_ok() {
+        try!(parse_file(&limits_conf, &mut limits));
+    }
+    let limits_d = util::join_root(root, LIMITS_D);
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(&limits_d) {
+        for entry in entries {
+            let file_name = try!(entry).file_name().to_string_lossy().into_owned();
+            if file_name.ends_with(".conf") {
+                files.push(format!("{}/{}", limits_d, file_name));
+            }
+        }
+    }
+    files.sort();
+    for file in &files {
+        try!(parse_file(file, &mut limits));
+    }
+    Ok(limits)
+}
+
+/// the priority of a domain for the user, lower is stronger (user, then groups and uid ranges,
+/// then `%group`, then `*`), None when the domain doesn't apply. Like pam_limits, group and
+/// wildcard domains don't apply to root. `:1000` matches only uid 1000, like `1000:1000`.
+/// gid ranges (`@1000:`) are not supported.
+fn get_priority(domain: &str, username: &str, uid: i32, groups: &[String]) -> Option<u8> {
+    if domain == username {
+        return Some(0);
+    }
+    if domain.contains(':') && !domain.starts_with('@') && !domain.starts_with('%') {
+        let mut bounds = domain.splitn(2, ':');
+        let min = bounds.next().and_then(|b| b.parse::<i32>().ok());
+        let max = bounds.next().and_then(|b| b.parse::<i32>().ok());
+        let exact = max.is_some() && (min.is_none() || min == max);
+        let in_range = if exact {
+            max == Some(uid)
+        } else {
+            min.map_or(true, |m| uid >= m) && max.map_or(true, |m| uid <= m)
+        };
+        return match (in_range, exact) {
+            (false, _) => None,
+            (true, true) => Some(0),
+            (true, false) => Some(1)
+        };
+    }
+    if username == "root" {
+        return None;
+    }
+    if domain == "*" {
+        return Some(3);
+    }
+    if domain.starts_with('@') && groups.iter().any(|g| g == &domain[1..]) {
+        return Some(1);
+    }
+    if domain == "%" || (domain.starts_with('%') && groups.iter().any(|g| g == &domain[1..])) {
+        return Some(2);
+    }
+    None
+}
+
+/// the value pam_limits sets for the user, a domain with a stronger priority can't be
+/// overridden, one with the same priority is overridden by the next lines
+pub fn get_effective<'a>(limits: &'a Vec<Limit>,
+                         username: &str,
+                         uid: i32,
+                         groups: &[String],
+                         limit_type: &str,
+                         item: &str) -> Option<&'a Limit> {
+    let mut effective: Option<(u8, &Limit)> = None;
+    for limit in limits {
+        if limit.item != item || (limit.limit_type != limit_type && limit.limit_type != "-") {
+            continue;
+        }
+        if let Some(priority) = get_priority(&limit.domain, username, uid, groups) {
+            if effective.map_or(true, |(p, _)| priority <= p) {
+                effective = Some((priority, limit));
+            }
+        }
+    }
+    effective.map(|(_, l)| l)
+}
+
+/// `unlimited` and `infinity` are bigger than any number, so is `-1` except for `nice` and
+/// `priority` where it's a valid value
+pub fn parse_value(item: &str, value: &str) -> Option<i64> {
+    match value {
+        "unlimited" | "infinity" => Some(i64::MAX),
+        "-1" if item != "nice" && item != "priority" => Some(i64::MAX),
+        _ => value.parse::<i64>().ok()
+    }
+}
+
+fn check_limit(limit_type: &str,
+               setting: &str,
+               limits: &Vec<Limit>,
+               username: &str,
+               uid: i32,
+               groups: &[String],
+               result: &mut test::TestResult) -> Result<(), LimitsError> {
+    let test_name = format!("limits - {}", limit_type);
+    let (item, operator, value) = match util::split_comparison(setting) {
+        Some(c) => c,
+        None => return Err(LimitsError::Setting(setting.to_string()))
+    };
+    let expected = parse_value(item, value);
+    if operator != "=" && expected.is_none() {
+        return Err(LimitsError::Setting(setting.to_string()));
+    }
+    let effective = get_effective(limits, username, uid, groups, limit_type, item);
+    let condition = effective.map_or(false, |l| {
+        match (parse_value(item, &l.value), expected) {
+            (Some(actual), Some(expected)) => util::compare(operator, actual, expected),
+            _ => l.value == value
+        }
+    });
+    let actual = match effective {
+        Some(l) => format!("{}={} ({})", item, l.value, l.location()),
+        None => format!("{} not set", item)
+    };
+    test::update_test_result(condition, &test_name, &format!("{} {}{}{}", username, item, operator, value), &actual,
+                             "incorrect limit", result);
+    Ok(())
+}
+
+pub fn check_limits(root: &str,
+                    username: &str,
+                    uid: i32,
+                    groups: &[String],
+                    soft: &[&str],
+                    hard: &[&str]) -> Result<test::TestResult, LimitsError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let limits = try!(get_limits(root));
+    for setting in soft {
+        try!(check_limit("soft", setting, &limits, username, uid, groups, &mut result));
+    }
+    for setting in hard {
+        try!(check_limit("hard", setting, &limits, username, uid, groups, &mut result));
+    }
+    Ok(result)
+}
+
+/// the groups of the user (primary and secondary) come from the group module
+pub fn check(username: &str, soft: &[&str], hard: &[&str]) -> Result<test::TestResult, LimitsError> {
+    let user = match try!(user::get_user(username)) {
+        Some(u) => u,
+        None => return Err(LimitsError::UnknownUser(username.to_string()))
+    };
+    let mut groups = user.groups.clone();
+    groups.push(user.group.clone());
+    check_limits("/", username, user.uid, &groups, soft, hard)
+}
+
+// TESTS
+
+#[cfg(test)]
+const ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/limits");
+
+#[cfg(test)]
+fn groups(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn get_limits_test_success() {
+    let limits = get_limits(ROOT_FIXTURE).unwrap();
+    assert_eq!(limits.len(), 10);
+    assert_eq!(limits[6].domain, "1000:");
+    assert_eq!(limits[6].value, "4096");
+    assert!(limits[7].file.ends_with("limits.d/90-postgres.conf"));
+    assert!(get_limits("/notexists").unwrap().is_empty());
+}
+
+#[test]
+fn get_priority_test_success() {
+    let dba = groups(&["alice", "dba"]);
+    assert_eq!(get_priority("alice", "alice", 1000, &dba), Some(0));
+    assert_eq!(get_priority("1000:1000", "alice", 1000, &dba), Some(0));
+    assert_eq!(get_priority("1000:", "alice", 1000, &dba), Some(1));
+    assert_eq!(get_priority(":999", "alice", 1000, &dba), None);
+    assert_eq!(get_priority(":999", "postgres", 998, &dba), None);
+    assert_eq!(get_priority(":999", "postgres", 999, &dba), Some(0));
+    assert_eq!(get_priority("@dba", "alice", 1000, &dba), Some(1));
+    assert_eq!(get_priority("%dba", "alice", 1000, &dba), Some(2));
+    assert_eq!(get_priority("*", "alice", 1000, &dba), Some(3));
+    assert_eq!(get_priority("@wheel", "alice", 1000, &dba), None);
+    assert_eq!(get_priority("*", "root", 0, &groups(&["root"])), None);
+}
+
+#[test]
+fn parse_value_test_success() {
+    assert_eq!(parse_value("nofile", "unlimited"), Some(i64::MAX));
+    assert_eq!(parse_value("nofile", "-1"), Some(i64::MAX));
+    assert_eq!(parse_value("nofile", "1024"), Some(1024));
+    assert_eq!(parse_value("nice", "-1"), Some(-1));
+    assert_eq!(parse_value("priority", "-1"), Some(-1));
+    assert_eq!(parse_value("nofile", "many"), None);
+}
+
+#[test]
+fn get_effective_test_success() {
+    let limits = get_limits(ROOT_FIXTURE).unwrap();
+    let postgres = groups(&["postgres", "dba"]);
+    // the user line of limits.d is not overridden by the @dba line read after it
+    assert_eq!(get_effective(&limits, "postgres", 999, &postgres, "hard", "nofile").unwrap().value, "65536");
+    assert_eq!(get_effective(&limits, "postgres", 999, &postgres, "soft", "memlock").unwrap().value, "unlimited");
+    assert!(get_effective(&limits, "postgres", 999, &postgres, "hard", "nproc").is_none());
+    let alice = groups(&["alice", "dba"]);
+    assert_eq!(get_effective(&limits, "alice", 1000, &alice, "hard", "nofile").unwrap().value, "16384");
+    assert_eq!(get_effective(&limits, "alice", 1000, &alice, "soft", "nofile").unwrap().value, "32768");
+    assert!(get_effective(&limits, "root", 0, &groups(&["root"]), "soft", "nofile").is_none());
+}
+
+#[test]
+fn check_limits_test_success() {
+    let result = check_limits(ROOT_FIXTURE, "postgres", 999, &groups(&["postgres", "dba"]),
+                              &["nofile>=65536", "memlock=unlimited", "core=0"], &["nofile=65536"]).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    let result = check_limits(ROOT_FIXTURE, "bob", 1001, &groups(&["bob"]), &["nofile<=1024"],
+                              &["nofile>=4096", "nproc=4096"]).unwrap();
+    assert_eq!(result.success, 3);
+}
+
+#[test]
+fn check_limits_test_error() {
+    let result = check_limits(ROOT_FIXTURE, "bob", 1001, &groups(&["bob"]), &["nofile>=65536", "memlock=unlimited"],
+                              &[]).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 2);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "limits - soft");
+            assert_eq!(e.expected, "bob nofile>=65536");
+            assert_eq!(e.actual, format!("nofile=1024 ({}/etc/security/limits.conf:4)", ROOT_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "memlock not set"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_limits(ROOT_FIXTURE, "bob", 1001, &[], &["nofile"], &[]).is_err());
+    assert!(check_limits(ROOT_FIXTURE, "bob", 1001, &[], &["nofile>=many"], &[]).is_err());
+}
@@ -0,0 +1,178 @@
+use std::fs;
+use std::i64;
+use std::io;
+use std::io::prelude::*;
+use modules::test;
+use util;
+
+const LOGIN_DEFS: &'static str = "/etc/login.defs";
+
+#[derive(Debug)]
+pub enum LoginDefsError {
+    Io(io::Error),
+    Setting(String)
+}
+
+impl From<io::Error> for LoginDefsError {
+    fn from(err: io::Error) -> LoginDefsError {
+        LoginDefsError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+    pub file: String,
+    pub line: usize
+}
+
+impl Setting {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+pub fn get_settings(path: &str) -> Result<Vec<Setting>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut settings = Vec::new();
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.splitn(2, char::is_whitespace);
+        let key = fields.next().unwrap_or("");
+        let value = fields.next().unwrap_or("").trim().trim_matches('"');
+        settings.push(Setting {
+            key: key.to_string(),
+            value: value.to_string(),
+            file: path.to_string(),
+            line: i + 1
+        });
+    }
+    Ok(settings)
+}
+
+/// like shadow's getdef, the last definition wins
+pub fn get_effective<'a>(settings: &'a Vec<Setting>, key: &str) -> Option<&'a Setting> {
+    settings.iter().rev().find(|s| s.key == key)
+}
+
+/// numbers are read like strtol with base 0: `022` is octal, `0x10` is hexadecimal
+pub fn parse_number(value: &str) -> Option<i64> {
+    let (negative, digits) = if value.starts_with('-') { (true, &value[1..]) } else { (false, value) };
+    let number = if digits.starts_with("0x") || digits.starts_with("0X") {
+        i64::from_str_radix(&digits[2..], 16).ok()
+    }
+    else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8).ok()
+    }
+    else {
+        digits.parse::<i64>().ok()
+    };
+    number.map(|n| if negative { -n } else { n })
+}
+
+fn check_setting(setting: &str, settings: &Vec<Setting>, result: &mut test::TestResult) -> Result<(), LoginDefsError> {
+    let test_name = "login_defs - setting";
+    let (key, operator, value) = match util::split_comparison(setting) {
+        Some(c) => c,
+        None => return Err(LoginDefsError::Setting(setting.to_string()))
+    };
+    let expected = parse_number(value);
+    if operator != "=" && expected.is_none() {
+        return Err(LoginDefsError::Setting(setting.to_string()));
+    }
+    let effective = get_effective(settings, key);
+    let condition = effective.map_or(false, |s| {
+        match (parse_number(&s.value), expected) {
+            (Some(actual), Some(expected)) => util::compare(operator, actual, expected),
+            _ => s.value == value
+        }
+    });
+    let actual = match effective {
+        Some(s) => format!("{} {} ({})", key, s.value, s.location()),
+        None => format!("{} not set", key)
+    };
+    test::update_test_result(condition, test_name, &format!("{}{}{}", key, operator, value), &actual,
+                             "incorrect setting", result);
+    Ok(())
+}
+
+pub fn check_login_defs(path: &str, settings: &[&str]) -> Result<test::TestResult, LoginDefsError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let defined = try!(get_settings(path));
+    for setting in settings {
+        try!(check_setting(setting, &defined, &mut result));
+    }
+    Ok(result)
+}
+
+pub fn check(settings: &[&str]) -> Result<test::TestResult, LoginDefsError> {
+    check_login_defs(LOGIN_DEFS, settings)
+}
+
+// TESTS
+
+#[cfg(test)]
+const LOGIN_DEFS_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/login_defs/login.defs");
+
+#[test]
+fn get_settings_test_success() {
+    let settings = get_settings(LOGIN_DEFS_FIXTURE).unwrap();
+    assert_eq!(settings.len(), 11);
+    assert_eq!(settings[0].key, "MAIL_DIR");
+    assert_eq!(settings[0].value, "/var/mail");
+    let effective = get_effective(&settings, "PASS_MAX_DAYS").unwrap();
+    assert_eq!(effective.value, "90");
+    assert_eq!(effective.line, 16);
+    assert!(get_effective(&settings, "SHA_CRYPT_MIN_ROUNDS").is_none());
+    assert!(get_settings("/notexists").is_err());
+}
+
+#[test]
+fn parse_number_test_success() {
+    assert_eq!(parse_number("027"), Some(23));
+    assert_eq!(parse_number("0x10"), Some(16));
+    assert_eq!(parse_number("60000"), Some(60000));
+    assert_eq!(parse_number("0"), Some(0));
+    assert_eq!(parse_number("-1"), Some(-1));
+    assert_eq!(parse_number("SHA512"), None);
+    assert_eq!(parse_number("09"), None);
+}
+
+#[test]
+fn check_login_defs_test_success() {
+    let result = check_login_defs(LOGIN_DEFS_FIXTURE, &["PASS_MAX_DAYS<=90", "UMASK=0027", "UMASK=027",
+                                                        "ENCRYPT_METHOD=SHA512", "UID_MIN>=1000",
+                                                        "USERGROUPS_ENAB=yes"]).unwrap();
+    assert_eq!(result.success, 6);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_login_defs_test_error() {
+    let result = check_login_defs(LOGIN_DEFS_FIXTURE, &["PASS_MIN_DAYS>=1", "UMASK=077", "SHA_CRYPT_MIN_ROUNDS>=5000",
+                                                        "ENCRYPT_METHOD=YESCRYPT"]).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 4);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "UMASK=077");
+            assert_eq!(e.actual, format!("UMASK 027 ({}:17)", LOGIN_DEFS_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[2] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "SHA_CRYPT_MIN_ROUNDS not set"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_login_defs(LOGIN_DEFS_FIXTURE, &["UMASK>=strict"]).is_err());
+}
@@ -3,3 +3,27 @@ pub mod test;
 pub mod group;
 pub mod package;
 pub mod file;
+pub mod audit;
+pub mod mount;
+pub mod filesystem_usage;
+pub mod kernel_param;
+pub mod kernel_module;
+pub mod process;
+pub mod port;
+pub mod service;
+pub mod interface;
+pub mod route;
+pub mod dns;
+pub mod command;
+pub mod http;
+pub mod reachable;
+pub mod certificate;
+pub mod sshd_config;
+pub mod sudoers;
+pub mod cron;
+pub mod systemd_unit;
+pub mod pam;
+pub mod limits;
+pub mod login_defs;
+pub mod config;
+pub mod structured_file;
@@ -0,0 +1,333 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str;
+use modules::test;
+use util;
+
+const MOUNTINFO: &'static str = "/proc/self/mountinfo";
+const FSTAB: &'static str = "/etc/fstab";
+
+/// fstab options which only drive mount(8) and never show up in mountinfo
+const FSTAB_ONLY_OPTIONS: [&'static str; 12] = ["defaults", "auto", "noauto", "user", "users", "nouser",
+                                                 "owner", "group", "nofail", "_netdev", "sw", "rw"];
+
+#[derive(Debug)]
+pub enum MountError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError)
+}
+
+impl From<io::Error> for MountError {
+    fn from(err: io::Error) -> MountError {
+        MountError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for MountError {
+    fn from(err: str::ParseBoolError) -> MountError {
+        MountError::ParseBool(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Mount {
+    pub mount_point: String,
+    pub source: String,
+    pub fstype: String,
+    pub options: Vec<String>
+}
+
+#[derive(Debug)]
+pub struct FstabEntry {
+    pub source: String,
+    pub mount_point: String,
+    pub fstype: String,
+    pub options: Vec<String>
+}
+
+/// decodes the octal escapes (\040 for a space...) used in mountinfo and fstab
+pub fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let code = str::from_utf8(&bytes[i + 1..i + 4]).ok()
+                .and_then(|c| u8::from_str_radix(c, 8).ok());
+            if let Some(c) = code {
+                result.push(c);
+                i += 4;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// parses a /proc/self/mountinfo line
+/// mount options and superblock options are merged in `options`
+pub fn parse_mountinfo_line(line: &str) -> Option<Mount> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let separator = match fields.iter().position(|f| *f == "-") {
+        Some(p) => p,
+        None => return None
+    };
+    if separator < 6 || fields.len() < separator + 3 {
+        return None;
+    }
+    let mut options: Vec<String> = fields[5].split(',').map(|o| o.to_string()).collect();
+    if fields.len() > separator + 3 {
+        for option in fields[separator + 3].split(',') {
+            if !options.contains(&option.to_string()) {
+                options.push(option.to_string());
+            }
+        }
+    }
+    Some(Mount {
+        mount_point: unescape(fields[4]),
+        source: unescape(fields[separator + 2]),
+        fstype: fields[separator + 1].to_string(),
+        options: options
+    })
+}
+
+/// returns the mounts listed in a mountinfo file
+pub fn get_mounts(path: &str) -> Result<Vec<Mount>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut mounts = Vec::new();
+    for l in reader.lines() {
+        let line = try!(l);
+        if let Some(mount) = parse_mountinfo_line(&line) {
+            mounts.push(mount);
+        }
+    }
+    Ok(mounts)
+}
+
+/// returns the mount visible on a mount point (the last one when mounts are stacked)
+pub fn get_mount(path: &str, mount_point: &str) -> Result<Option<Mount>, io::Error> {
+    let mounts = try!(get_mounts(path));
+    Ok(mounts.into_iter().filter(|m| m.mount_point == mount_point).last())
+}
+
+/// returns the entries of a fstab file
+pub fn get_fstab_entries(path: &str) -> Result<Vec<FstabEntry>, io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut entries = Vec::new();
+    for l in reader.lines() {
+        let line = try!(l);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 {
+            continue;
+        }
+        let options = if fields.len() > 3 {
+            fields[3].split(',').map(|o| o.to_string()).collect()
+        }
+        else {
+            vec!["defaults".to_string()]
+        };
+        entries.push(FstabEntry {
+            source: unescape(fields[0]),
+            mount_point: unescape(fields[1]),
+            fstype: fields[2].to_string(),
+            options: options
+        });
+    }
+    Ok(entries)
+}
+
+/// resolves UUID=, LABEL=, PARTUUID= and symlinked device paths to the device path
+pub fn resolve_source(source: &str) -> String {
+    let tags = [("UUID=", "/dev/disk/by-uuid/"),
+                ("LABEL=", "/dev/disk/by-label/"),
+                ("PARTUUID=", "/dev/disk/by-partuuid/")];
+    let mut device = source.to_string();
+    for &(tag, dir) in tags.iter() {
+        if source.starts_with(tag) {
+            device = format!("{}{}", dir, &source[tag.len()..]);
+        }
+    }
+    match fs::canonicalize(Path::new(&device)) {
+        Ok(p) => p.to_string_lossy().into_owned(),
+        Err(_) => device
+    }
+}
+
+fn check_source(source: &str, mount: &Mount, result: &mut test::TestResult) {
+    let test_name = "mount - source";
+    let condition = source == mount.source || resolve_source(source) == resolve_source(&mount.source);
+    test::update_test_result(condition, test_name, source, &mount.source, "incorrect source", result);
+}
+
+fn check_fstype(fstype: &str, mount: &Mount, result: &mut test::TestResult) {
+    let test_name = "mount - fstype";
+    let condition = fstype == mount.fstype;
+    test::update_test_result(condition, test_name, fstype, &mount.fstype, "incorrect filesystem type", result);
+}
+
+fn check_options(options: &str, present: bool, mount: &Mount, result: &mut test::TestResult) {
+    let test_name = "mount - options";
+    let actual = mount.options.join(",");
+    for option in options.split(',').filter(|o| !o.is_empty()) {
+        let condition = mount.options.contains(&option.to_string()) == present;
+        let expected = format!("{}: {}", option, if present { "present" } else { "absent" });
+        let message = if present { "required option missing" } else { "forbidden option set" };
+        test::update_test_result(condition, test_name, &expected, &actual, message, result);
+    }
+}
+
+fn check_fstab(fstab: &Vec<FstabEntry>, mount: &Mount, result: &mut test::TestResult) {
+    let entry = fstab.iter().filter(|e| e.mount_point == mount.mount_point).last();
+    test::check_exists(&entry, true, result, "mount - fstab".to_string());
+    if let Some(entry) = entry {
+        let test_name = "mount - fstab fstype";
+        let condition = entry.fstype == mount.fstype;
+        test::update_test_result(condition, test_name, &entry.fstype, &mount.fstype,
+                                 "filesystem type differs from fstab", result);
+        let options: Vec<String> = entry.options.iter()
+            .filter(|o| !o.contains('=') && !FSTAB_ONLY_OPTIONS.contains(&o.as_ref()) && !o.starts_with("x-"))
+            .map(|o| o.to_string())
+            .collect();
+        if !options.is_empty() {
+            check_options(&options.join(","), true, mount, result);
+        }
+    }
+}
+
+pub fn check_mount(mountinfo: &str,
+                   fstab: &str,
+                   mount_point: &str,
+                   exists: &str,
+                   source: Option<&str>,
+                   fstype: Option<&str>,
+                   options: Option<&str>,
+                   forbidden_options: Option<&str>,
+                   in_fstab: Option<&str>) -> Result<test::TestResult, MountError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists_bool: bool = try!(exists.parse());
+    let in_fstab = try!(util::parse_bool(in_fstab)).unwrap_or(false);
+    let mount_result = try!(get_mount(mountinfo, mount_point));
+    test::check_exists(&mount_result, exists_bool, &mut result, "mount - exists".to_string());
+    match mount_result {
+        None => Ok(result),
+        Some(mount) => {
+            source.map(|s| {
+                check_source(s, &mount, &mut result);
+            });
+            fstype.map(|t| {
+                check_fstype(t, &mount, &mut result);
+            });
+            options.map(|o| {
+                check_options(o, true, &mount, &mut result);
+            });
+            forbidden_options.map(|o| {
+                check_options(o, false, &mount, &mut result);
+            });
+            if in_fstab {
+                let entries = try!(get_fstab_entries(fstab));
+                check_fstab(&entries, &mount, &mut result);
+            }
+            Ok(result)
+        }
+    }
+}
+
+pub fn check(mount_point: &str,
+             exists: &str,
+             source: Option<&str>,
+             fstype: Option<&str>,
+             options: Option<&str>,
+             forbidden_options: Option<&str>,
+             in_fstab: Option<&str>) -> Result<test::TestResult, MountError> {
+    check_mount(MOUNTINFO, FSTAB, mount_point, exists, source, fstype, options, forbidden_options, in_fstab)
+}
+
+// TESTS
+
+#[cfg(test)]
+const MOUNTINFO_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/mount/mountinfo");
+#[cfg(test)]
+const FSTAB_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/mount/fstab");
+
+#[test]
+fn parse_mountinfo_line_test_success() {
+    let line = "20 17 0:35 / /tmp rw,nosuid,nodev,noexec,relatime shared:20 - tmpfs tmpfs rw,size=1048576k";
+    let mount = parse_mountinfo_line(line).unwrap();
+    assert_eq!(mount.mount_point, "/tmp");
+    assert_eq!(mount.source, "tmpfs");
+    assert_eq!(mount.fstype, "tmpfs");
+    assert_eq!(mount.options, vec!["rw", "nosuid", "nodev", "noexec", "relatime", "size=1048576k"]);
+    let line = "23 17 8:3 / /mnt/my\\040disk rw - ext4 /dev/sda3 rw";
+    let mount = parse_mountinfo_line(line).unwrap();
+    assert_eq!(mount.mount_point, "/mnt/my disk");
+    assert!(parse_mountinfo_line("20 17 0:35 / /tmp rw").is_none());
+}
+
+#[test]
+fn get_mount_test_success() {
+    let mount = get_mount(MOUNTINFO_FIXTURE, "/data").unwrap().unwrap();
+    assert_eq!(mount.fstype, "xfs");
+    assert_eq!(mount.source, "/dev/sdb1");
+    let mount = get_mount(MOUNTINFO_FIXTURE, "/srv").unwrap().unwrap();
+    assert_eq!(mount.fstype, "nfs4");
+    assert!(get_mount(MOUNTINFO_FIXTURE, "/home").unwrap().is_none());
+}
+
+#[test]
+fn get_fstab_entries_test_success() {
+    let entries = get_fstab_entries(FSTAB_FIXTURE).unwrap();
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[1].source, "UUID=2f6a3c5e-1b8e-4c0a-9d3e-6a1b2c3d4e5f");
+    assert_eq!(entries[1].mount_point, "/data");
+    assert_eq!(entries[3].mount_point, "/mnt/my disk");
+    assert_eq!(entries[4].options, vec!["nosuid", "nodev", "noexec", "size=1G"]);
+}
+
+#[test]
+fn check_mount_test_success() {
+    let result = check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/tmp", "true", None, Some("tmpfs"),
+                             Some("nodev,nosuid,noexec"), Some("exec"), Some("true")).unwrap();
+    assert_eq!(result.error, 0);
+    // exists, fstype, 3 options, 1 forbidden option, fstab, fstab fstype, 3 fstab options
+    assert_eq!(result.success, 11);
+    let result = check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/data", "true", Some("/dev/sdb1"), Some("xfs"),
+                             None, None, Some("true")).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 6);
+    let result = check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/home", "false", None, Some("ext4"),
+                             None, None, None).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_mount_test_error() {
+    let result = check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/var/log", "true", Some("/dev/sdb1"), Some("xfs"),
+                             Some("noexec"), Some("relatime"), None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 4);
+    match result.summary[3] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "noexec: present");
+            assert_eq!(e.actual, "rw,relatime,data=ordered");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/srv", "true", None, None, None, None, Some("true")).unwrap();
+    assert_eq!(result.error, 1);
+    assert!(check_mount(MOUNTINFO_FIXTURE, FSTAB_FIXTURE, "/tmp", "hello", None, None, None, None, None).is_err());
+}
@@ -0,0 +1,371 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::ptr;
+use std::str;
+use distrib;
+use modules::test;
+use util;
+
+const PAM_DIR: &'static str = "etc/pam.d";
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum PamError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    IncludeDepth(String),
+    /// no default PAM file for the distribution, --service must be given
+    Distrib(String)
+}
+
+impl From<io::Error> for PamError {
+    fn from(err: io::Error) -> PamError {
+        PamError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for PamError {
+    fn from(err: str::ParseBoolError) -> PamError {
+        PamError::ParseBool(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// auth, account, password or session, without the leading `-`
+    pub pam_type: String,
+    /// a keyword (required) or a bracketed list with its whitespace collapsed ([success=1 default=ignore])
+    pub control: String,
+    /// as written in the file, can be a full path
+    pub module: String,
+    pub args: Vec<String>,
+    pub file: String,
+    pub line: usize
+}
+
+impl Entry {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+
+    /// `pam_unix`, `pam_unix.so` and `/usr/lib64/security/pam_unix.so` are the same module
+    pub fn is_module(&self, module: &str) -> bool {
+        let name = self.module.rsplit('/').next().unwrap_or("");
+        name == module || name.trim_end_matches(".so") == module.trim_end_matches(".so")
+    }
+}
+
+/// splits on whitespace, a bracketed value (`[success=1 default=ignore]`) is a single field
+pub fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_brackets = false;
+    for c in line.chars() {
+        match c {
+            '[' if current.is_empty() => {
+                in_brackets = true;
+                current.push(c);
+            }
+            ']' if in_brackets => {
+                in_brackets = false;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_brackets => {
+                if !current.is_empty() {
+                    fields.push(current.clone());
+                    current.clear();
+                }
+            }
+            c if c.is_whitespace() => {
+                if !current.ends_with(' ') && !current.ends_with('[') {
+                    current.push(' ');
+                }
+            }
+            _ => current.push(c)
+        }
+    }
+    if !current.is_empty() {
+        fields.push(current);
+    }
+    fields
+}
+
+/// the entries of a PAM file, `@include` (Debian) and the `include` and `substack` controls
+/// (Red Hat) are replaced by the entries of the included file, only the ones of the same type
+/// for `include` and `substack`
+fn parse_file(pam_dir: &str, name: &str, pam_type: Option<&str>, depth: usize, entries: &mut Vec<Entry>)
+              -> Result<(), PamError> {
+    let path = if name.starts_with('/') { name.to_string() } else { format!("{}/{}", pam_dir, name) };
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(PamError::IncludeDepth(path));
+    }
+    let file = try!(fs::File::open(&path));
+    let reader = io::BufReader::new(file);
+    let mut pending: Option<(String, usize)> = None;
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let (line, start) = match pending.take() {
+            Some((previous, start)) => (format!("{} {}", previous, line.trim()), start),
+            None => (line.trim().to_string(), i + 1)
+        };
+        if line.ends_with('\\') {
+            pending = Some((line.trim_end_matches('\\').trim().to_string(), start));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_fields(&line);
+        if fields[0] == "@include" {
+            if fields.len() > 1 {
+                try!(parse_file(pam_dir, &fields[1], pam_type, depth + 1, entries));
+            }
+            continue;
+        }
+        if fields.len() < 3 {
+            continue;
+        }
+        let entry_type = fields[0].trim_start_matches('-').to_lowercase();
+        if pam_type.map_or(false, |t| t != entry_type) {
+            continue;
+        }
+        if fields[1] == "include" || fields[1] == "substack" {
+            try!(parse_file(pam_dir, &fields[2], Some(&entry_type), depth + 1, entries));
+            continue;
+        }
+        entries.push(Entry {
+            pam_type: entry_type,
+            control: fields[1].clone(),
+            module: fields[2].clone(),
+            args: fields[3..].to_vec(),
+            file: path.clone(),
+            line: start
+        });
+    }
+    Ok(())
+}
+
+/// the entries of a type, in the order PAM runs them, for the service file `name` of /etc/pam.d
+pub fn get_stack(root: &str, name: &str, pam_type: &str) -> Result<Vec<Entry>, PamError> {
+    let mut entries = Vec::new();
+    try!(parse_file(&util::join_root(root, PAM_DIR), name, Some(pam_type), 0, &mut entries));
+    Ok(entries)
+}
+
+/// the file shared by the services: common-<type> for Debian, system-auth for Red Hat
+pub fn get_default_file(distrib: &str, pam_type: &str) -> Result<String, PamError> {
+    match distrib {
+        "debian" => Ok(format!("common-{}", pam_type)),
+        "rhel" | "centos" => Ok("system-auth".to_string()),
+        _ => Err(PamError::Distrib(distrib.to_string()))
+    }
+}
+
+fn describe(entries: &Vec<&Entry>) -> String {
+    entries.iter()
+        .map(|e| {
+            let mut fields = vec![e.control.clone(), e.module.clone()];
+            fields.extend(e.args.iter().cloned());
+            format!("{} ({})", fields.join(" "), e.location())
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// every argument must be set, `deny=5` is compared as a whole
+fn has_args(entry: &Entry, args: &Vec<&str>) -> bool {
+    args.iter().all(|a| entry.args.iter().any(|e| e == a))
+}
+
+fn check_args(args: &Vec<&str>, candidates: &Vec<&Entry>, result: &mut test::TestResult) {
+    let test_name = "pam - arguments";
+    let condition = candidates.iter().any(|e| has_args(e, args));
+    test::update_test_result(condition, test_name, &args.join(" "), &describe(candidates), "missing arguments", result);
+}
+
+fn check_control(control: &str, candidates: &Vec<&Entry>, result: &mut test::TestResult) {
+    let test_name = "pam - control";
+    let control = split_fields(control).join(" ");
+    let condition = candidates.iter().any(|e| e.control == control);
+    test::update_test_result(condition, test_name, &control, &describe(candidates), "incorrect control", result);
+}
+
+/// `before` means before the first entry of the other module, `after` after its last entry
+fn check_position(module: &str,
+                  other: &str,
+                  before: bool,
+                  stack: &Vec<Entry>,
+                  candidates: &Vec<&Entry>,
+                  result: &mut test::TestResult) {
+    let test_name = "pam - position";
+    let relation = if before { "before" } else { "after" };
+    let expected = format!("{} {} {}", module, relation, other);
+    let others: Vec<usize> = stack.iter().enumerate().filter(|&(_, e)| e.is_module(other)).map(|(i, _)| i).collect();
+    let positions: Vec<usize> = stack.iter()
+        .enumerate()
+        .filter(|&(_, e)| candidates.iter().any(|c| ptr::eq(*c, e)))
+        .map(|(i, _)| i)
+        .collect();
+    let condition = match (others.first(), others.last()) {
+        (Some(first), Some(last)) => positions.iter().any(|p| if before { p < first } else { p > last }),
+        _ => false
+    };
+    let actual = if others.is_empty() {
+        format!("{} not found", other)
+    }
+    else {
+        format!("{} at {:?}, {} at {:?}", module, positions, other, others)
+    };
+    test::update_test_result(condition, test_name, &expected, &actual, "incorrect position", result);
+}
+
+pub fn check_pam(root: &str,
+                 distrib: &str,
+                 pam_type: &str,
+                 service: Option<&str>,
+                 module: &str,
+                 exists: Option<&str>,
+                 control: Option<&str>,
+                 args: Option<&str>,
+                 before: Option<&str>,
+                 after: Option<&str>) -> Result<test::TestResult, PamError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists = try!(util::parse_bool(exists)).unwrap_or(true);
+    let file = match service {
+        Some(s) => s.to_string(),
+        None => try!(get_default_file(distrib, pam_type))
+    };
+    let stack = try!(get_stack(root, &file, pam_type));
+    let mut candidates: Vec<&Entry> = stack.iter().filter(|e| e.is_module(module)).collect();
+    let found = if candidates.is_empty() { None } else { Some(()) };
+    test::check_exists(&found, exists, &mut result, "pam - module".to_string());
+    if candidates.is_empty() {
+        return Ok(result);
+    }
+    if let Some(args) = args {
+        let args: Vec<&str> = args.split(',').filter(|a| !a.is_empty()).collect();
+        check_args(&args, &candidates, &mut result);
+        // the other tests apply to the entries having the arguments (preauth or authfail for pam_faillock)
+        if candidates.iter().any(|e| has_args(e, &args)) {
+            candidates.retain(|e| has_args(e, &args));
+        }
+    }
+    control.map(|c| {
+        check_control(c, &candidates, &mut result);
+    });
+    before.map(|b| {
+        check_position(module, b, true, &stack, &candidates, &mut result);
+    });
+    after.map(|a| {
+        check_position(module, a, false, &stack, &candidates, &mut result);
+    });
+    Ok(result)
+}
+
+/// without `service`, the file of the distribution shared by the services is tested
+pub fn check(pam_type: &str,
+             service: Option<&str>,
+             module: &str,
+             exists: Option<&str>,
+             control: Option<&str>,
+             args: Option<&str>,
+             before: Option<&str>,
+             after: Option<&str>) -> Result<test::TestResult, PamError> {
+    check_pam("/", &distrib::get_distrib(), pam_type, service, module, exists, control, args, before, after)
+}
+
+// TESTS
+
+#[cfg(test)]
+const DEBIAN_ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/pam/debian");
+#[cfg(test)]
+const RHEL_ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/pam/rhel");
+
+#[test]
+fn split_fields_test_success() {
+    assert_eq!(split_fields("auth\t[success=1   default=ignore]\tpam_unix.so nullok"),
+               vec!["auth", "[success=1 default=ignore]", "pam_unix.so", "nullok"]);
+    assert_eq!(split_fields("session optional pam_exec.so [quiet  log]"),
+               vec!["session", "optional", "pam_exec.so", "[quiet log]"]);
+}
+
+#[test]
+fn get_stack_test_success() {
+    let stack = get_stack(DEBIAN_ROOT_FIXTURE, "sshd", "auth").unwrap();
+    assert_eq!(stack.len(), 6);
+    assert_eq!(stack[1].control, "[success=1 default=ignore]");
+    assert!(stack[1].file.ends_with("common-auth"));
+    let stack = get_stack(DEBIAN_ROOT_FIXTURE, "sshd", "session").unwrap();
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack[1].module, "pam_systemd.so");
+    let stack = get_stack(DEBIAN_ROOT_FIXTURE, "common-password", "password").unwrap();
+    assert_eq!(stack[0].args, vec!["retry=3", "minlen=14", "dcredit=-1", "ucredit=-1"]);
+    assert_eq!(stack[1].line, 3);
+    let stack = get_stack(RHEL_ROOT_FIXTURE, "sshd", "auth").unwrap();
+    assert_eq!(stack.iter().map(|e| e.module.as_str()).collect::<Vec<&str>>(),
+               vec!["pam_env.so", "/usr/lib64/security/pam_unix.so", "pam_deny.so", "pam_lastlog.so"]);
+    assert!(stack[1].is_module("pam_unix"));
+    assert!(get_stack(RHEL_ROOT_FIXTURE, "notexists", "auth").is_err());
+}
+
+#[test]
+fn check_pam_test_success() {
+    let result = check_pam(DEBIAN_ROOT_FIXTURE, "debian", "auth", None, "pam_faillock.so", None, Some("required"),
+                           Some("preauth"), Some("pam_unix.so"), None).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    let result = check_pam(DEBIAN_ROOT_FIXTURE, "debian", "auth", Some("sshd"), "pam_faillock", None,
+                           Some("[default=die]"), Some("authfail"), None, Some("pam_unix")).unwrap();
+    assert_eq!(result.success, 4);
+    let result = check_pam(DEBIAN_ROOT_FIXTURE, "debian", "password", None, "pam_pwquality.so", None, Some("requisite"),
+                           Some("minlen=14,dcredit=-1"), None, None).unwrap();
+    assert_eq!(result.success, 3);
+    let result = check_pam(RHEL_ROOT_FIXTURE, "rhel", "auth", None, "pam_faillock.so", Some("true"), Some("required"),
+                           Some("preauth,deny=5,unlock_time=900"), Some("pam_unix.so"), Some("pam_env.so")).unwrap();
+    assert_eq!(result.success, 5);
+    assert_eq!(result.error, 0);
+    let result = check_pam(RHEL_ROOT_FIXTURE, "centos", "auth", Some("sshd"), "pam_faillock.so", Some("false"), None,
+                           None, None, None).unwrap();
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_pam_test_error() {
+    let result = check_pam(DEBIAN_ROOT_FIXTURE, "debian", "auth", None, "pam_faillock.so", None, Some("requisite"),
+                           Some("preauth,deny=5"), Some("pam_deny.so"), Some("pam_permit.so")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 3);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "preauth deny=5");
+            assert_eq!(e.actual, format!("required pam_faillock.so preauth ({0}/etc/pam.d/common-auth:2),\
+                                          [default=die] pam_faillock.so authfail ({0}/etc/pam.d/common-auth:4),\
+                                          sufficient pam_faillock.so authsucc ({0}/etc/pam.d/common-auth:5)",
+                                         DEBIAN_ROOT_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[4] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "pam_faillock.so after pam_permit.so");
+            assert_eq!(e.actual, "pam_faillock.so at [0, 2, 3], pam_permit.so at [5]");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_pam(RHEL_ROOT_FIXTURE, "rhel", "auth", Some("sshd"), "pam_faillock.so", None, Some("required"),
+                           None, None, None).unwrap();
+    assert_eq!(result.error, 1);
+    assert_eq!(result.summary.len(), 1);
+    let result = check_pam(RHEL_ROOT_FIXTURE, "rhel", "auth", None, "pam_env.so", None, None, None, None,
+                           Some("pam_tally2.so")).unwrap();
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "pam_tally2.so not found"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_pam(RHEL_ROOT_FIXTURE, "unknow", "auth", None, "pam_env.so", None, None, None, None, None).is_err());
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, AddrParseError};
+use std::num;
+use std::str;
+use modules::test;
+use modules::process;
+use util;
+
+const PROC: &'static str = "/proc";
+
+/// TCP_LISTEN in /proc/net/tcp and tcp6
+const TCP_LISTEN: &'static str = "0A";
+/// __SO_ACCEPTCON in /proc/net/unix
+const UNIX_ACCEPTCON: u32 = 0x10000;
+
+#[derive(Debug)]
+pub enum PortError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    ParseBool(str::ParseBoolError),
+    AddrParse(AddrParseError),
+    InvalidProtocol(String)
+}
+
+impl From<io::Error> for PortError {
+    fn from(err: io::Error) -> PortError {
+        PortError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for PortError {
+    fn from(err: num::ParseIntError) -> PortError {
+        PortError::ParseInt(err)
+    }
+}
+
+impl From<str::ParseBoolError> for PortError {
+    fn from(err: str::ParseBoolError) -> PortError {
+        PortError::ParseBool(err)
+    }
+}
+
+impl From<AddrParseError> for PortError {
+    fn from(err: AddrParseError) -> PortError {
+        PortError::AddrParse(err)
+    }
+}
+
+/// a listening socket; unix sockets have a path instead of an address and a port
+#[derive(Debug)]
+pub struct Socket {
+    pub address: Option<IpAddr>,
+    pub port: i32,
+    pub path: String,
+    pub inode: u64
+}
+
+impl Socket {
+    pub fn display(&self) -> String {
+        match self.address {
+            Some(IpAddr::V4(ref a)) => format!("{}:{}", a, self.port),
+            Some(IpAddr::V6(ref a)) => format!("[{}]:{}", a, self.port),
+            None => self.path.to_string()
+        }
+    }
+}
+
+/// decodes an address of /proc/net/tcp or tcp6: 32 bit words in host order (little-endian)
+pub fn parse_hex_address(hex: &str) -> Result<IpAddr, num::ParseIntError> {
+    let mut bytes = Vec::new();
+    let mut i = 0;
+    while i + 8 <= hex.len() {
+        let word = try!(u32::from_str_radix(&hex[i..i + 8], 16));
+        for shift in 0..4 {
+            bytes.push(((word >> (8 * shift)) & 0xff) as u8);
+        }
+        i += 8;
+    }
+    if bytes.len() == 4 {
+        Ok(IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])))
+    }
+    else {
+        let mut segments = [0u16; 8];
+        for k in 0..8 {
+            let high = *bytes.get(2 * k).unwrap_or(&0) as u16;
+            let low = *bytes.get(2 * k + 1).unwrap_or(&0) as u16;
+            segments[k] = (high << 8) | low;
+        }
+        Ok(IpAddr::V6(Ipv6Addr::new(segments[0], segments[1], segments[2], segments[3],
+                                    segments[4], segments[5], segments[6], segments[7])))
+    }
+}
+
+/// returns the listening sockets of a /proc/net/{tcp,tcp6,udp,udp6} file
+/// udp sockets without remote address are considered listening
+pub fn get_inet_sockets(path: &str, tcp: bool) -> Result<Vec<Socket>, PortError> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        // no IPv6 support
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(PortError::Io(e))
+    };
+    let reader = io::BufReader::new(file);
+    let mut sockets = Vec::new();
+    for l in reader.lines().skip(1) {
+        let line = try!(l);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let listening = if tcp {
+            fields[3] == TCP_LISTEN
+        }
+        else {
+            fields[2].split(':').nth(1) == Some("0000")
+        };
+        if !listening {
+            continue;
+        }
+        let local: Vec<&str> = fields[1].split(':').collect();
+        if local.len() != 2 {
+            continue;
+        }
+        sockets.push(Socket {
+            address: Some(try!(parse_hex_address(local[0]))),
+            port: try!(i32::from_str_radix(local[1], 16)),
+            path: "".to_string(),
+            inode: try!(fields[9].parse::<u64>())
+        });
+    }
+    Ok(sockets)
+}
+
+/// returns the listening sockets of /proc/net/unix
+pub fn get_unix_sockets(path: &str) -> Result<Vec<Socket>, PortError> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut sockets = Vec::new();
+    for l in reader.lines().skip(1) {
+        let line = try!(l);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let flags = try!(u32::from_str_radix(fields[3], 16));
+        if flags & UNIX_ACCEPTCON == 0 {
+            continue;
+        }
+        sockets.push(Socket {
+            address: None,
+            port: 0,
+            path: fields[7].to_string(),
+            inode: try!(fields[6].parse::<u64>())
+        });
+    }
+    Ok(sockets)
+}
+
+/// maps socket inodes to the pid holding them, using the /proc/[pid]/fd links
+/// processes we are not allowed to inspect are skipped
+pub fn get_socket_owners(proc_dir: &str) -> Result<HashMap<u64, i32>, io::Error> {
+    let mut owners = HashMap::new();
+    for entry in try!(fs::read_dir(proc_dir)) {
+        let entry = try!(entry);
+        let pid = match entry.file_name().to_string_lossy().parse::<i32>() {
+            Ok(p) => p,
+            Err(_) => continue
+        };
+        let fds = match fs::read_dir(entry.path().join("fd")) {
+            Ok(f) => f,
+            Err(_) => continue
+        };
+        for fd in fds {
+            let link = match fd.and_then(|f| fs::read_link(f.path())) {
+                Ok(l) => l.to_string_lossy().into_owned(),
+                Err(_) => continue
+            };
+            if link.starts_with("socket:[") && link.ends_with("]") {
+                if let Ok(inode) = link[8..link.len() - 1].parse::<u64>() {
+                    owners.insert(inode, pid);
+                }
+            }
+        }
+    }
+    Ok(owners)
+}
+
+fn get_listening_sockets(proc_dir: &str, protocol: &str) -> Result<Vec<Socket>, PortError> {
+    match protocol {
+        "tcp" | "udp" => {
+            let tcp = protocol == "tcp";
+            let mut sockets = try!(get_inet_sockets(&format!("{}/net/{}", proc_dir, protocol), tcp));
+            let sockets6 = try!(get_inet_sockets(&format!("{}/net/{}6", proc_dir, protocol), tcp));
+            sockets.extend(sockets6);
+            Ok(sockets)
+        },
+        "unix" => get_unix_sockets(&format!("{}/net/unix", proc_dir)),
+        _ => Err(PortError::InvalidProtocol(protocol.to_string()))
+    }
+}
+
+fn check_address(address: &IpAddr, sockets: &Vec<&Socket>, result: &mut test::TestResult) {
+    let test_name = "port - address";
+    let condition = !sockets.is_empty() && sockets.iter().all(|s| s.address == Some(*address));
+    let expected = format!("listening only on {}", address);
+    let actual = sockets.iter().map(|s| s.display()).collect::<Vec<String>>().join(", ");
+    test::update_test_result(condition, test_name, &expected, &actual, "listening on unexpected addresses", result);
+}
+
+fn check_process(proc_dir: &str, name: &str, socket: &Socket, owners: &HashMap<u64, i32>, result: &mut test::TestResult) {
+    let test_name = "port - process";
+    let owner = owners.get(&socket.inode)
+        .and_then(|pid| process::get_process(proc_dir, *pid).ok())
+        .and_then(|p| p);
+    let (condition, actual) = match owner {
+        Some(p) => (p.comm == name, format!("{}: pid {} ({})", socket.display(), p.pid, p.comm)),
+        None => (false, format!("{}: unknown process", socket.display()))
+    };
+    test::update_test_result(condition, test_name, name, &actual, "socket owned by another process", result);
+}
+
+pub fn check_port(proc_dir: &str,
+                  protocol: Option<&str>,
+                  port: Option<&str>,
+                  path: Option<&str>,
+                  listening: &str,
+                  address: Option<&str>,
+                  process_name: Option<&str>) -> Result<test::TestResult, PortError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let protocol = protocol.unwrap_or("tcp");
+    let listening_bool: bool = try!(listening.parse());
+    let port = try!(util::parse_int(port));
+    let address = match address {
+        Some(a) => Some(try!(a.parse::<IpAddr>())),
+        None => None
+    };
+    let sockets = try!(get_listening_sockets(proc_dir, protocol));
+    let matched: Vec<&Socket> = sockets.iter()
+        .filter(|s| port.map(|p| s.port == p).unwrap_or(true))
+        .filter(|s| path.map(|p| s.path == p).unwrap_or(true))
+        .collect();
+    let found = if matched.is_empty() { None } else { Some(()) };
+    test::check_exists(&found, listening_bool, &mut result, "port - listening".to_string());
+    if matched.is_empty() {
+        return Ok(result);
+    }
+    address.map(|a| {
+        check_address(&a, &matched, &mut result);
+    });
+    if let Some(name) = process_name {
+        let owners = try!(get_socket_owners(proc_dir));
+        for socket in &matched {
+            check_process(proc_dir, name, socket, &owners, &mut result);
+        }
+    }
+    Ok(result)
+}
+
+pub fn check(protocol: Option<&str>,
+             port: Option<&str>,
+             path: Option<&str>,
+             listening: &str,
+             address: Option<&str>,
+             process_name: Option<&str>) -> Result<test::TestResult, PortError> {
+    check_port(PROC, protocol, port, path, listening, address, process_name)
+}
+
+// TESTS
+
+#[cfg(test)]
+const PROC_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/port/proc");
+
+#[test]
+fn parse_hex_address_test_success() {
+    assert_eq!(parse_hex_address("0100007F").unwrap(), "127.0.0.1".parse::<IpAddr>().unwrap());
+    assert_eq!(parse_hex_address("0500000A").unwrap(), "10.0.0.5".parse::<IpAddr>().unwrap());
+    assert_eq!(parse_hex_address("00000000000000000000000001000000").unwrap(), "::1".parse::<IpAddr>().unwrap());
+    assert_eq!(parse_hex_address("B80D0120000000000000000001000000").unwrap(), "2001:db8::1".parse::<IpAddr>().unwrap());
+    assert!(parse_hex_address("hello!!!").is_err());
+}
+
+#[test]
+fn get_inet_sockets_test_success() {
+    let sockets = get_inet_sockets(&format!("{}/net/tcp", PROC_FIXTURE), true).unwrap();
+    assert_eq!(sockets.len(), 3);
+    assert_eq!(sockets[0].display(), "127.0.0.1:5432");
+    assert_eq!(sockets[0].inode, 1001);
+    assert_eq!(sockets[2].display(), "0.0.0.0:3306");
+    let sockets = get_inet_sockets(&format!("{}/net/udp", PROC_FIXTURE), false).unwrap();
+    assert_eq!(sockets.len(), 1);
+    assert_eq!(sockets[0].display(), "0.0.0.0:53");
+    let sockets = get_inet_sockets(&format!("{}/net/tcp6", PROC_FIXTURE), true).unwrap();
+    assert_eq!(sockets[0].display(), "[::]:22");
+    assert!(get_inet_sockets(&format!("{}/net/notexists", PROC_FIXTURE), true).unwrap().is_empty());
+}
+
+#[test]
+fn get_unix_sockets_test_success() {
+    let sockets = get_unix_sockets(&format!("{}/net/unix", PROC_FIXTURE)).unwrap();
+    assert_eq!(sockets.len(), 2);
+    assert_eq!(sockets[0].path, "/run/postgresql/.s.PGSQL.5432");
+    assert_eq!(sockets[0].inode, 1006);
+}
+
+#[test]
+fn get_socket_owners_test_success() {
+    let owners = get_socket_owners(PROC_FIXTURE).unwrap();
+    assert_eq!(owners.get(&1001), Some(&20));
+    assert_eq!(owners.get(&1004), Some(&10));
+    assert_eq!(owners.get(&9999), None);
+}
+
+#[test]
+fn check_port_test_success() {
+    let result = check_port(PROC_FIXTURE, None, Some("5432"), None, "true", Some("127.0.0.1"), Some("postgres")).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 3);
+    let result = check_port(PROC_FIXTURE, Some("udp"), Some("53"), None, "true", None, Some("dnsmasq")).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 2);
+    let result = check_port(PROC_FIXTURE, Some("unix"), None, Some("/run/postgresql/.s.PGSQL.5432"), "true", None, Some("postgres")).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 2);
+    let result = check_port(PROC_FIXTURE, None, Some("23"), None, "false", None, None).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_port_test_error() {
+    let result = check_port(PROC_FIXTURE, None, Some("3306"), None, "true", Some("127.0.0.1"), None).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "listening only on 127.0.0.1");
+            assert_eq!(e.actual, "0.0.0.0:3306, [::1]:3306");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_port(PROC_FIXTURE, None, Some("22"), None, "false", None, None).unwrap();
+    assert_eq!(result.error, 1);
+    let result = check_port(PROC_FIXTURE, None, Some("22"), None, "true", None, Some("telnetd")).unwrap();
+    assert_eq!(result.error, 2);
+    assert!(check_port(PROC_FIXTURE, Some("sctp"), Some("22"), None, "true", None, None).is_err());
+    assert!(check_port(PROC_FIXTURE, None, Some("22"), None, "true", Some("localhost"), None).is_err());
+}
@@ -0,0 +1,750 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::num;
+use std::str;
+use regex::{self, Regex};
+use modules::test;
+use modules::user;
+use modules::group;
+use modules::limits;
+use util;
+
+const PROC: &'static str = "/proc";
+
+/// lines of /proc/[pid]/limits, with the item names of limits.conf
+const LIMIT_NAMES: [(&'static str, &'static str); 16] = [("Max cpu time", "cpu"), ("Max file size", "fsize"),
+                                                         ("Max data size", "data"), ("Max stack size", "stack"),
+                                                         ("Max core file size", "core"), ("Max resident set", "rss"),
+                                                         ("Max processes", "nproc"), ("Max open files", "nofile"),
+                                                         ("Max locked memory", "memlock"),
+                                                         ("Max address space", "as"), ("Max file locks", "locks"),
+                                                         ("Max pending signals", "sigpending"),
+                                                         ("Max msgqueue size", "msgqueue"),
+                                                         ("Max nice priority", "nice"),
+                                                         ("Max realtime priority", "rtprio"),
+                                                         ("Max realtime timeout", "rttime")];
+
+/// capability bits, see capability.h
+const CAPABILITIES: [&'static str; 41] = ["chown", "dac_override", "dac_read_search", "fowner", "fsetid", "kill",
+                                          "setgid", "setuid", "setpcap", "linux_immutable", "net_bind_service",
+                                          "net_broadcast", "net_admin", "net_raw", "ipc_lock", "ipc_owner",
+                                          "sys_module", "sys_rawio", "sys_chroot", "sys_ptrace", "sys_pacct",
+                                          "sys_admin", "sys_boot", "sys_nice", "sys_resource", "sys_time",
+                                          "sys_tty_config", "mknod", "lease", "audit_write", "audit_control",
+                                          "setfcap", "mac_override", "mac_admin", "syslog", "wake_alarm",
+                                          "block_suspend", "audit_read", "perfmon", "bpf", "checkpoint_restore"];
+
+#[derive(Debug)]
+pub enum ProcessError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error),
+    User(user::UserError),
+    Group(group::GroupError),
+    Setting(String)
+}
+
+impl From<io::Error> for ProcessError {
+    fn from(err: io::Error) -> ProcessError {
+        ProcessError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for ProcessError {
+    fn from(err: num::ParseIntError) -> ProcessError {
+        ProcessError::ParseInt(err)
+    }
+}
+
+impl From<str::ParseBoolError> for ProcessError {
+    fn from(err: str::ParseBoolError) -> ProcessError {
+        ProcessError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for ProcessError {
+    fn from(err: regex::Error) -> ProcessError {
+        ProcessError::Regex(err)
+    }
+}
+
+impl From<user::UserError> for ProcessError {
+    fn from(err: user::UserError) -> ProcessError {
+        ProcessError::User(err)
+    }
+}
+
+impl From<group::GroupError> for ProcessError {
+    fn from(err: group::GroupError) -> ProcessError {
+        ProcessError::Group(err)
+    }
+}
+
+/// the options of the process command, as given on the command line
+#[derive(Debug, Default)]
+pub struct Options<'a> {
+    pub name: Option<&'a str>,
+    pub cmdline: Option<&'a str>,
+    pub count: Option<&'a str>,
+    pub min_count: Option<&'a str>,
+    pub max_count: Option<&'a str>,
+    pub user: Option<&'a str>,
+    pub group: Option<&'a str>,
+    pub parent: Option<&'a str>,
+    pub zombie: Option<&'a str>,
+    pub soft_limits: &'a [&'a str],
+    pub hard_limits: &'a [&'a str],
+    pub env: &'a [&'a str],
+    pub cwd: Option<&'a str>,
+    pub capabilities: Option<&'a str>
+}
+
+#[derive(Debug)]
+pub struct Process {
+    pub pid: i32,
+    pub ppid: i32,
+    pub comm: String,
+    pub state: char,
+    pub cmdline: Vec<String>,
+    pub uid: i32,
+    pub gid: i32
+}
+
+/// the matchers selecting processes
+pub enum ProcessFilter {
+    Name(String),
+    Cmdline(Regex)
+}
+
+impl Process {
+    pub fn is_zombie(&self) -> bool {
+        self.state == 'Z'
+    }
+
+    pub fn matches(&self, filter: &ProcessFilter) -> bool {
+        match *filter {
+            ProcessFilter::Name(ref name) => self.comm == *name,
+            ProcessFilter::Cmdline(ref regex) => regex.is_match(&self.cmdline.join(" "))
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<String, io::Error> {
+    let mut file = try!(fs::File::open(path));
+    let mut content = String::new();
+    try!(file.read_to_string(&mut content));
+    Ok(content)
+}
+
+/// parses /proc/[pid]/stat, returns (comm, state, ppid)
+/// comm is between parenthesis and may itself contain spaces or parenthesis
+pub fn parse_stat(stat: &str) -> Option<(String, char, i32)> {
+    let start = match stat.find('(') {
+        Some(s) => s,
+        None => return None
+    };
+    let end = match stat.rfind(')') {
+        Some(e) => e,
+        None => return None
+    };
+    if end < start {
+        return None;
+    }
+    let comm = stat[start + 1..end].to_string();
+    let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+    if fields.len() < 2 {
+        return None;
+    }
+    let state = match fields[0].chars().next() {
+        Some(s) => s,
+        None => return None
+    };
+    let ppid = match fields[1].parse::<i32>() {
+        Ok(p) => p,
+        Err(_) => return None
+    };
+    Some((comm, state, ppid))
+}
+
+/// returns the effective id of an `Uid:` or `Gid:` line of /proc/[pid]/status
+pub fn get_status_id(status: &str, key: &str) -> Option<i32> {
+    for line in status.lines() {
+        if line.starts_with(key) {
+            let ids: Vec<&str> = line[key.len()..].split_whitespace().collect();
+            if ids.len() > 1 {
+                return ids[1].parse::<i32>().ok();
+            }
+        }
+    }
+    None
+}
+
+/// returns (item, soft, hard) for each line of /proc/[pid]/limits
+pub fn parse_limits(limits: &str) -> Vec<(String, String, String)> {
+    let mut parsed = Vec::new();
+    for line in limits.lines() {
+        if let Some(&(name, item)) = LIMIT_NAMES.iter().find(|&&(n, _)| line.starts_with(n)) {
+            let values: Vec<&str> = line[name.len()..].split_whitespace().collect();
+            if values.len() > 1 {
+                parsed.push((item.to_string(), values[0].to_string(), values[1].to_string()));
+            }
+        }
+    }
+    parsed
+}
+
+/// returns the variables of /proc/[pid]/environ
+pub fn parse_environ(environ: &str) -> Vec<(String, String)> {
+    environ.split('\0')
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            let mut parts = v.splitn(2, '=');
+            (parts.next().unwrap_or("").to_string(), parts.next().unwrap_or("").to_string())
+        })
+        .collect()
+}
+
+/// `cap_net_bind_service` for bit 10, `cap_<bit>` for the bits unknown to this list
+pub fn decode_capabilities(mask: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| match CAPABILITIES.get(bit as usize) {
+            Some(name) => format!("cap_{}", name),
+            None => format!("cap_{}", bit)
+        })
+        .collect()
+}
+
+/// returns the names of the effective capabilities (`CapEff:` of /proc/[pid]/status)
+pub fn get_capabilities(status: &str) -> Option<Vec<String>> {
+    status.lines()
+        .find(|l| l.starts_with("CapEff:"))
+        .and_then(|l| u64::from_str_radix(l["CapEff:".len()..].trim(), 16).ok())
+        .map(decode_capabilities)
+}
+
+/// returns the process of a /proc/[pid] directory
+pub fn get_process(proc_dir: &str, pid: i32) -> Result<Option<Process>, io::Error> {
+    let stat = try!(read_file(&format!("{}/{}/stat", proc_dir, pid)));
+    let status = try!(read_file(&format!("{}/{}/status", proc_dir, pid)));
+    let cmdline = try!(read_file(&format!("{}/{}/cmdline", proc_dir, pid)));
+    let (comm, state, ppid) = match parse_stat(&stat) {
+        Some(s) => s,
+        None => return Ok(None)
+    };
+    let uid = get_status_id(&status, "Uid:");
+    let gid = get_status_id(&status, "Gid:");
+    match (uid, gid) {
+        (Some(uid), Some(gid)) => Ok(Some(Process {
+            pid: pid,
+            ppid: ppid,
+            comm: comm,
+            state: state,
+            cmdline: cmdline.split('\0').filter(|a| !a.is_empty()).map(|a| a.to_string()).collect(),
+            uid: uid,
+            gid: gid
+        })),
+        _ => Ok(None)
+    }
+}
+
+/// returns the processes of a proc directory, sorted by pid
+/// processes exiting while they are read are skipped
+pub fn get_processes(proc_dir: &str) -> Result<Vec<Process>, io::Error> {
+    let mut pids = Vec::new();
+    for entry in try!(fs::read_dir(proc_dir)) {
+        let entry = try!(entry);
+        if let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>() {
+            pids.push(pid);
+        }
+    }
+    pids.sort();
+    let mut processes = Vec::new();
+    for pid in pids {
+        if let Ok(Some(process)) = get_process(proc_dir, pid) {
+            processes.push(process);
+        }
+    }
+    Ok(processes)
+}
+
+fn check_count(count: usize, min: Option<i32>, max: Option<i32>, result: &mut test::TestResult) {
+    let test_name = "process - count";
+    let min = min.unwrap_or(0);
+    let condition = count as i32 >= min && max.map(|m| count as i32 <= m).unwrap_or(true);
+    let expected = match max {
+        Some(m) if m == min => format!("{}", m),
+        Some(m) => format!("between {} and {}", min, m),
+        None => format!("at least {}", min)
+    };
+    test::update_test_result(condition, test_name, &expected, &count.to_string(),
+                             "incorrect number of processes", result);
+}
+
+fn check_user(username: &str, uid: Option<i32>, process: &Process, result: &mut test::TestResult) {
+    let test_name = "process - user";
+    let condition = uid == Some(process.uid);
+    let actual = format!("pid {}: uid {}", process.pid, process.uid);
+    test::update_test_result(condition, test_name, username, &actual, "incorrect effective user", result);
+}
+
+fn check_group(groupname: &str, gid: Option<i32>, process: &Process, result: &mut test::TestResult) {
+    let test_name = "process - group";
+    let condition = gid == Some(process.gid);
+    let actual = format!("pid {}: gid {}", process.pid, process.gid);
+    test::update_test_result(condition, test_name, groupname, &actual, "incorrect effective group", result);
+}
+
+fn check_parent(parent: &str, process: &Process, processes: &Vec<Process>, result: &mut test::TestResult) {
+    let test_name = "process - parent";
+    let parent_comm = processes.iter()
+        .find(|p| p.pid == process.ppid)
+        .map(|p| p.comm.to_string())
+        .unwrap_or("".to_string());
+    let actual = format!("pid {}: parent {} ({})", process.pid, process.ppid, parent_comm);
+    test::update_test_result(parent_comm == parent, test_name, parent, &actual, "incorrect parent process", result);
+}
+
+fn check_zombie(zombie: bool, process: &Process, result: &mut test::TestResult) {
+    let test_name = "process - zombie";
+    let actual = format!("pid {}: {}", process.pid, process.is_zombie());
+    test::update_test_result(process.is_zombie() == zombie, test_name, &zombie.to_string(), &actual,
+                             "incorrect zombie state", result);
+}
+
+/// `nofile>=65536`, values are in the units of /proc/[pid]/limits (bytes for memlock)
+fn parse_limit_setting(setting: &str) -> Result<(&str, &str, &str), ProcessError> {
+    match util::split_comparison(setting) {
+        Some((item, operator, value)) if LIMIT_NAMES.iter().any(|&(_, i)| i == item)
+                                         && (operator == "=" || limits::parse_value(item, value).is_some()) => {
+            Ok((item, operator, value))
+        }
+        _ => Err(ProcessError::Setting(setting.to_string()))
+    }
+}
+
+fn parse_env_setting(setting: &str) -> Result<(&str, &str), ProcessError> {
+    match setting.find('=') {
+        Some(i) if i > 0 => Ok((&setting[..i], &setting[i + 1..])),
+        _ => Err(ProcessError::Setting(setting.to_string()))
+    }
+}
+
+/// files of /proc/[pid] can't be read for the processes of other users without privileges,
+/// the test fails with the reason
+fn unreadable(process: &Process, file: &str, error: &io::Error) -> String {
+    format!("pid {}: can't read {}: {}", process.pid, file, error)
+}
+
+fn check_limit(limit_type: &str,
+               setting: &(&str, &str, &str),
+               process: &Process,
+               proc_limits: &Result<Vec<(String, String, String)>, io::Error>,
+               result: &mut test::TestResult) {
+    let test_name = format!("process - {} limit", limit_type);
+    let &(item, operator, value) = setting;
+    let expected = format!("{}{}{}", item, operator, value);
+    let actual_value = match *proc_limits {
+        Ok(ref l) => l.iter()
+            .find(|&&(ref i, _, _)| i == item)
+            .map(|&(_, ref soft, ref hard)| if limit_type == "soft" { soft.to_string() } else { hard.to_string() }),
+        Err(ref e) => {
+            test::update_test_result(false, &test_name, &expected, &unreadable(process, "limits", e),
+                                     "incorrect limit", result);
+            return;
+        }
+    };
+    let condition = actual_value.as_ref().map_or(false, |a| {
+        match (limits::parse_value(item, a), limits::parse_value(item, value)) {
+            (Some(actual), Some(expected)) => util::compare(operator, actual, expected),
+            _ => a == value
+        }
+    });
+    let actual = format!("pid {}: {}={}", process.pid, item, actual_value.unwrap_or("".to_string()));
+    test::update_test_result(condition, &test_name, &expected, &actual, "incorrect limit", result);
+}
+
+fn check_env(setting: &(&str, &str),
+             process: &Process,
+             environ: &Result<Vec<(String, String)>, io::Error>,
+             result: &mut test::TestResult) {
+    let test_name = "process - env";
+    let &(key, value) = setting;
+    let expected = format!("{}={}", key, value);
+    let (condition, actual) = match *environ {
+        Ok(ref e) => match e.iter().find(|&&(ref k, _)| k == key) {
+            Some(&(_, ref v)) => (v == value, format!("pid {}: {}={}", process.pid, key, v)),
+            None => (false, format!("pid {}: {} not set", process.pid, key))
+        },
+        Err(ref e) => (false, unreadable(process, "environ", e))
+    };
+    test::update_test_result(condition, test_name, &expected, &actual, "incorrect environment variable", result);
+}
+
+fn check_cwd(cwd: &str, process: &Process, proc_dir: &str, result: &mut test::TestResult) {
+    let test_name = "process - cwd";
+    let (condition, actual) = match fs::read_link(format!("{}/{}/cwd", proc_dir, process.pid)) {
+        Ok(path) => (path.to_string_lossy() == cwd, format!("pid {}: {}", process.pid, path.to_string_lossy())),
+        Err(ref e) => (false, unreadable(process, "cwd", e))
+    };
+    test::update_test_result(condition, test_name, cwd, &actual, "incorrect working directory", result);
+}
+
+/// the effective capabilities must be exactly the expected ones, empty for none
+fn check_capabilities(capabilities: &str, process: &Process, proc_dir: &str, result: &mut test::TestResult) {
+    let test_name = "process - capabilities";
+    let mut expected: Vec<String> = capabilities.split(',')
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .map(|c| if c.starts_with("cap_") { c } else { format!("cap_{}", c) })
+        .collect();
+    expected.sort();
+    let (condition, actual) = match read_file(&format!("{}/{}/status", proc_dir, process.pid)) {
+        Ok(status) => {
+            let mut actual = get_capabilities(&status).unwrap_or(Vec::new());
+            actual.sort();
+            (actual == expected, format!("pid {}: {}", process.pid, actual.join(",")))
+        }
+        Err(ref e) => (false, unreadable(process, "status", e))
+    };
+    test::update_test_result(condition, test_name, &expected.join(","), &actual, "incorrect capabilities", result);
+}
+
+pub fn check_process(proc_dir: &str, options: &Options) -> Result<test::TestResult, ProcessError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let mut filters = Vec::new();
+    if let Some(name) = options.name {
+        filters.push(ProcessFilter::Name(name.to_string()));
+    }
+    if let Some(cmdline) = options.cmdline {
+        filters.push(ProcessFilter::Cmdline(try!(Regex::new(cmdline))));
+    }
+    let count = try!(util::parse_int(options.count));
+    let min_count = try!(util::parse_int(options.min_count)).or(count);
+    let max_count = try!(util::parse_int(options.max_count)).or(count);
+    let zombie = try!(util::parse_bool(options.zombie));
+    let soft_limits = try!(options.soft_limits.iter().map(|s| parse_limit_setting(s)).collect::<Result<Vec<_>, _>>());
+    let hard_limits = try!(options.hard_limits.iter().map(|s| parse_limit_setting(s)).collect::<Result<Vec<_>, _>>());
+    let env = try!(options.env.iter().map(|s| parse_env_setting(s)).collect::<Result<Vec<_>, _>>());
+    let uid = match options.user {
+        Some(u) => try!(user::get_user(u)).map(|u| u.uid),
+        None => None
+    };
+    let gid = match options.group {
+        Some(g) => try!(group::get_group_from_name(g)).map(|g| g.gid),
+        None => None
+    };
+
+    let processes = try!(get_processes(proc_dir));
+    let matched: Vec<&Process> = processes.iter()
+        .filter(|p| filters.iter().all(|f| p.matches(f)))
+        .collect();
+    if min_count.is_some() || max_count.is_some() {
+        check_count(matched.len(), min_count, max_count, &mut result);
+    }
+    // the assertions on each process can't be checked without a process, this is an error
+    let per_process = options.user.is_some() || options.group.is_some() || options.parent.is_some()
+                      || zombie.is_some() || !soft_limits.is_empty() || !hard_limits.is_empty() || !env.is_empty()
+                      || options.cwd.is_some() || options.capabilities.is_some();
+    if per_process && matched.is_empty() {
+        test::update_test_result(false, "process - matched", "a matching process", "no process",
+                                 "no process to check", &mut result);
+    }
+    for process in matched {
+        options.user.map(|u| {
+            check_user(u, uid, process, &mut result);
+        });
+        options.group.map(|g| {
+            check_group(g, gid, process, &mut result);
+        });
+        options.parent.map(|p| {
+            check_parent(p, process, &processes, &mut result);
+        });
+        zombie.map(|z| {
+            check_zombie(z, process, &mut result);
+        });
+        if !soft_limits.is_empty() || !hard_limits.is_empty() {
+            let proc_limits = read_file(&format!("{}/{}/limits", proc_dir, process.pid)).map(|l| parse_limits(&l));
+            for setting in &soft_limits {
+                check_limit("soft", setting, process, &proc_limits, &mut result);
+            }
+            for setting in &hard_limits {
+                check_limit("hard", setting, process, &proc_limits, &mut result);
+            }
+        }
+        if !env.is_empty() {
+            let environ = read_file(&format!("{}/{}/environ", proc_dir, process.pid)).map(|e| parse_environ(&e));
+            for setting in &env {
+                check_env(setting, process, &environ, &mut result);
+            }
+        }
+        options.cwd.map(|c| {
+            check_cwd(c, process, proc_dir, &mut result);
+        });
+        options.capabilities.map(|c| {
+            check_capabilities(c, process, proc_dir, &mut result);
+        });
+    }
+    Ok(result)
+}
+
+pub fn check(options: &Options) -> Result<test::TestResult, ProcessError> {
+    check_process(PROC, options)
+}
+
+// TESTS
+
+#[cfg(test)]
+const PROC_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/process/proc");
+
+#[test]
+fn parse_stat_test_success() {
+    let (comm, state, ppid) = parse_stat("300 (tmux: server) S 1 300 300 0 -1").unwrap();
+    assert_eq!(comm, "tmux: server");
+    assert_eq!(state, 'S');
+    assert_eq!(ppid, 1);
+    let (comm, _, _) = parse_stat("42 (a) b)) R 7 42").unwrap();
+    assert_eq!(comm, "a) b)");
+    assert!(parse_stat("42 a R 7").is_none());
+}
+
+#[test]
+fn get_processes_test_success() {
+    let processes = get_processes(PROC_FIXTURE).unwrap();
+    assert_eq!(processes.len(), 6);
+    let master = &processes[1];
+    assert_eq!(master.pid, 100);
+    assert_eq!(master.ppid, 1);
+    assert_eq!(master.comm, "nginx");
+    assert_eq!(master.cmdline, vec!["nginx: master process /usr/sbin/nginx -g daemon on;"]);
+    assert_eq!(master.uid, 0);
+    let worker = &processes[2];
+    assert_eq!(worker.uid, 33);
+    assert_eq!(worker.gid, 33);
+    assert!(processes[4].is_zombie());
+    assert!(processes[4].cmdline.is_empty());
+    assert_eq!(processes[5].cmdline, vec!["tmux", "new", "-s", "main"]);
+}
+
+#[test]
+fn check_process_test_success() {
+    let result = check_process(PROC_FIXTURE, &Options {
+        cmdline: Some("^nginx: master"),
+        count: Some("1"),
+        user: Some("root"),
+        group: Some("root"),
+        parent: Some("systemd"),
+        zombie: Some("false"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 5);
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("nginx"),
+        cmdline: Some("worker"),
+        min_count: Some("2"),
+        max_count: Some("8"),
+        parent: Some("nginx"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 3);
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("telnetd"),
+        max_count: Some("0"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 0);
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_process_test_error() {
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("nginx"),
+        cmdline: Some("worker"),
+        min_count: Some("3"),
+        user: Some("root"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 3);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "at least 3");
+            assert_eq!(e.actual, "2");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "root");
+            assert_eq!(e.actual, "pid 101: uid 33");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("sh"),
+        zombie: Some("false"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 1);
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("telnetd"),
+        user: Some("root"),
+        group: Some("root"),
+        parent: Some("systemd"),
+        zombie: Some("false"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "process - matched");
+            assert_eq!(e.actual, "no process");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_process(PROC_FIXTURE, &Options {
+        cmdline: Some("("),
+        ..Options::default()
+    }).is_err());
+    assert!(check_process(PROC_FIXTURE, &Options {
+        count: Some("one"),
+        ..Options::default()
+    }).is_err());
+}
+
+#[test]
+fn parse_limits_test_success() {
+    let limits = parse_limits(&read_file(&format!("{}/101/limits", PROC_FIXTURE)).unwrap());
+    assert_eq!(limits.len(), 16);
+    assert_eq!(limits[0], ("cpu".to_string(), "unlimited".to_string(), "unlimited".to_string()));
+    assert_eq!(limits[7], ("nofile".to_string(), "1024".to_string(), "524288".to_string()));
+    assert_eq!(limits[13], ("nice".to_string(), "0".to_string(), "0".to_string()));
+}
+
+#[test]
+fn parse_environ_test_success() {
+    let environ = parse_environ("PATH=/usr/bin\0OPTIONS=-g daemon=off\0EMPTY=\0");
+    assert_eq!(environ, vec![("PATH".to_string(), "/usr/bin".to_string()),
+                             ("OPTIONS".to_string(), "-g daemon=off".to_string()),
+                             ("EMPTY".to_string(), "".to_string())]);
+}
+
+#[test]
+fn get_capabilities_test_success() {
+    assert_eq!(get_capabilities("Uid:\t33\nCapEff:\t0000000000003400\n").unwrap(),
+               vec!["cap_net_bind_service", "cap_net_admin", "cap_net_raw"]);
+    assert_eq!(get_capabilities("CapEff:\t0000000000000000\n").unwrap(), Vec::<String>::new());
+    assert_eq!(decode_capabilities(1 << 10 | 1 << 63), vec!["cap_net_bind_service", "cap_63"]);
+    assert_eq!(decode_capabilities(0x1ffffffffff).len(), 41);
+    assert!(get_capabilities("Uid:\t33\n").is_none());
+}
+
+#[test]
+fn check_process_proc_files_test_success() {
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("nginx"),
+        cmdline: Some("worker"),
+        soft_limits: &["stack=8388608", "nproc>=4096"],
+        hard_limits: &["nofile>=65536", "cpu=unlimited"],
+        env: &["PATH=/usr/sbin:/usr/bin"],
+        cwd: Some("/var/www"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 12);
+    assert_eq!(result.error, 0);
+    let result = check_process(PROC_FIXTURE, &Options {
+        cmdline: Some("^nginx: worker"),
+        env: &["OPTIONS=-g daemon=off"],
+        capabilities: Some("net_bind_service"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 2);
+    let result = check_process(PROC_FIXTURE, &Options {
+        cmdline: Some("^nginx: master"),
+        cwd: Some("/"),
+        capabilities: Some(""),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+}
+
+#[test]
+fn check_process_proc_files_test_error() {
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("nginx"),
+        cmdline: Some("worker"),
+        soft_limits: &["nofile>=65536"],
+        env: &["NGINX_ENV=production"],
+        capabilities: Some("CAP_NET_BIND_SERVICE"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 3);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "process - soft limit");
+            assert_eq!(e.expected, "nofile>=65536");
+            assert_eq!(e.actual, "pid 101: nofile=1024");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[4] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "pid 102: NGINX_ENV=staging"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[5] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "cap_net_bind_service");
+            assert_eq!(e.actual, "pid 102: cap_net_admin,cap_net_bind_service,cap_net_raw");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("systemd"),
+        env: &["LANG=C"],
+        cwd: Some("/"),
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.error, 2);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert!(e.actual.starts_with("pid 1: can't read environ")),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_process(PROC_FIXTURE, &Options {
+        name: Some("telnetd"),
+        soft_limits: &["nofile>=1024"],
+        ..Options::default()
+    }).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "process - matched");
+            assert_eq!(e.actual, "no process");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_process(PROC_FIXTURE, &Options {
+        soft_limits: &["files>=1"],
+        ..Options::default()
+    }).is_err());
+    assert!(check_process(PROC_FIXTURE, &Options {
+        hard_limits: &["nofile>=lots"],
+        ..Options::default()
+    }).is_err());
+    assert!(check_process(PROC_FIXTURE, &Options {
+        env: &["LANG"],
+        ..Options::default()
+    }).is_err());
+}
@@ -0,0 +1,246 @@
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::num;
+use std::str;
+use std::time::Duration;
+use libc;
+use modules::test;
+use util;
+
+#[derive(Debug)]
+pub enum ReachableError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    Duration(util::DurationError),
+    ParseBool(str::ParseBoolError),
+    Protocol(String)
+}
+
+impl From<io::Error> for ReachableError {
+    fn from(err: io::Error) -> ReachableError {
+        ReachableError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for ReachableError {
+    fn from(err: num::ParseIntError) -> ReachableError {
+        ReachableError::ParseInt(err)
+    }
+}
+
+impl From<util::DurationError> for ReachableError {
+    fn from(err: util::DurationError) -> ReachableError {
+        ReachableError::Duration(err)
+    }
+}
+
+impl From<str::ParseBoolError> for ReachableError {
+    fn from(err: str::ParseBoolError) -> ReachableError {
+        ReachableError::ParseBool(err)
+    }
+}
+
+/// the outcome of a probe, Err contains why the endpoint is not reachable
+pub type Probe = Result<SocketAddr, String>;
+
+/// refused connections, timeouts, unreachable hosts or networks (firewall REJECT) and denied
+/// packets (local firewall DROP) mean the endpoint is not reachable, the other errors
+/// (resolution, local socket) mean that nothing was probed
+fn is_unreachable(error: &io::Error) -> bool {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        | io::ErrorKind::PermissionDenied => true,
+        _ => match error.raw_os_error() {
+            Some(libc::EHOSTUNREACH) | Some(libc::ENETUNREACH) => true,
+            _ => false
+        }
+    }
+}
+
+fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, io::Error> {
+    let addrs: Vec<SocketAddr> = try!((host, port).to_socket_addrs()).collect();
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no address for {}", host)));
+    }
+    Ok(addrs)
+}
+
+/// the endpoint is reachable when one of its addresses is, it is not reachable
+/// only when every address was probed
+fn probe_addrs<F>(host: &str, port: u16, probe_addr: F) -> Result<Probe, io::Error>
+    where F: Fn(&SocketAddr) -> Result<Probe, io::Error> {
+    let mut reasons = Vec::new();
+    let mut local_error = None;
+    for addr in try!(resolve(host, port)) {
+        match probe_addr(&addr) {
+            Ok(Ok(a)) => return Ok(Ok(a)),
+            Ok(Err(reason)) => reasons.push(reason),
+            Err(e) => {
+                if local_error.is_none() {
+                    local_error = Some(io::Error::new(e.kind(), format!("{}: {}", addr, e)));
+                }
+            }
+        }
+    }
+    match local_error {
+        Some(e) => Err(e),
+        None => Ok(Err(reasons.join(", ")))
+    }
+}
+
+pub fn probe_tcp(host: &str, port: u16, timeout: Duration) -> Result<Probe, io::Error> {
+    probe_addrs(host, port, |addr| {
+        match TcpStream::connect_timeout(addr, timeout) {
+            Ok(_) => Ok(Ok(*addr)),
+            Err(ref e) if is_unreachable(e) => Ok(Err(format!("{}: {}", addr, e))),
+            Err(e) => Err(e)
+        }
+    })
+}
+
+/// UDP is connectionless: the endpoint is reachable when it answers the payload
+/// (with a response containing `expect`, if given). A timeout counts as blocked.
+pub fn probe_udp(host: &str, port: u16, timeout: Duration, payload: &str, expect: Option<&str>)
+                 -> Result<Probe, io::Error> {
+    probe_addrs(host, port, |addr| probe_udp_addr(addr, timeout, payload, expect))
+}
+
+fn probe_udp_addr(addr: &SocketAddr, timeout: Duration, payload: &str, expect: Option<&str>)
+                  -> Result<Probe, io::Error> {
+    let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = try!(UdpSocket::bind(local));
+    try!(socket.set_read_timeout(Some(timeout)));
+    try!(socket.connect(addr));
+    let mut buf = [0; 65536];
+    match socket.send(payload.as_bytes()).and_then(|_| socket.recv(&mut buf)) {
+        Ok(size) => {
+            let response = String::from_utf8_lossy(&buf[..size]);
+            if expect.map_or(true, |e| response.contains(e)) {
+                Ok(Ok(*addr))
+            }
+            else {
+                Ok(Err(format!("{}: unexpected response {}", addr, response)))
+            }
+        }
+        Err(ref e) if is_unreachable(e) => Ok(Err(format!("{}: {}", addr, e))),
+        Err(e) => Err(e)
+    }
+}
+
+pub fn check(host: &str,
+             port: &str,
+             reachable: &str,
+             protocol: Option<&str>,
+             timeout: Option<&str>,
+             payload: Option<&str>,
+             expect: Option<&str>) -> Result<test::TestResult, ReachableError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let port = try!(port.parse::<u16>());
+    let reachable_bool: bool = try!(reachable.parse());
+    let timeout_duration = try!(util::parse_duration(timeout)).unwrap_or(Duration::from_secs(3));
+    let protocol = protocol.unwrap_or("tcp");
+    let probe = match protocol {
+        "tcp" => try!(probe_tcp(host, port, timeout_duration)),
+        "udp" => try!(probe_udp(host, port, timeout_duration, payload.unwrap_or(""), expect)),
+        _ => return Err(ReachableError::Protocol(protocol.to_string()))
+    };
+    let test_name = format!("reachable - {}", protocol);
+    let (condition, actual) = match probe {
+        Ok(addr) => (reachable_bool, format!("reached {}", addr)),
+        Err(e) => (!reachable_bool, e)
+    };
+    let message = if reachable_bool { "endpoint not reachable" } else { "endpoint reachable" };
+    test::update_test_result(condition, &test_name, &format!("{}:{} reachable {}", host, port, reachable),
+                             &actual, message, &mut result);
+    Ok(result)
+}
+
+// TESTS
+
+#[cfg(test)]
+use std::net::TcpListener;
+#[cfg(test)]
+use std::thread;
+
+#[test]
+fn is_unreachable_test_success() {
+    assert!(is_unreachable(&io::Error::from_raw_os_error(libc::EHOSTUNREACH)));
+    assert!(is_unreachable(&io::Error::from_raw_os_error(libc::ENETUNREACH)));
+    assert!(is_unreachable(&io::Error::from_raw_os_error(libc::EPERM)));
+    assert!(is_unreachable(&io::Error::from_raw_os_error(libc::ECONNREFUSED)));
+    assert!(is_unreachable(&io::Error::new(io::ErrorKind::TimedOut, "timeout")));
+    assert!(!is_unreachable(&io::Error::from_raw_os_error(libc::EADDRNOTAVAIL)));
+    assert!(!is_unreachable(&io::Error::new(io::ErrorKind::NotFound, "no address")));
+}
+
+#[test]
+fn probe_tcp_test_success() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    assert_eq!(probe_tcp("localhost", port, Duration::from_secs(1)).unwrap(),
+               Ok(SocketAddr::new("127.0.0.1".parse().unwrap(), port)));
+    drop(listener);
+    assert!(probe_tcp("127.0.0.1", port, Duration::from_secs(1)).unwrap().is_err());
+    assert!(probe_tcp("notexists.invalid", port, Duration::from_secs(1)).is_err());
+}
+
+#[test]
+fn probe_udp_test_success() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = server.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        for _ in 0..2 {
+            let (size, peer) = server.recv_from(&mut buf).unwrap();
+            let response = format!("pong {}", String::from_utf8_lossy(&buf[..size]));
+            server.send_to(response.as_bytes(), peer).unwrap();
+        }
+    });
+    let timeout = Duration::from_secs(1);
+    assert!(probe_udp("127.0.0.1", port, timeout, "ping", Some("pong ping")).unwrap().is_ok());
+    let probe = probe_udp("127.0.0.1", port, timeout, "ping", Some("pang")).unwrap();
+    assert_eq!(probe, Err(format!("127.0.0.1:{}: unexpected response pong ping", port)));
+}
+
+#[test]
+fn check_test_success() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    let result = check("127.0.0.1", &port, "true", None, Some("1"), None, None).unwrap();
+    assert_eq!(result.success, 1);
+    drop(listener);
+    let result = check("127.0.0.1", &port, "false", Some("tcp"), Some("1"), None, None).unwrap();
+    assert_eq!(result.success, 1);
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port().to_string();
+    drop(socket);
+    let result = check("127.0.0.1", &port, "false", Some("udp"), Some("0.2"), Some("ping"), None).unwrap();
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_test_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    let result = check("127.0.0.1", &port, "false", None, None, None, None).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, format!("127.0.0.1:{} reachable false", port));
+            assert_eq!(e.actual, format!("reached 127.0.0.1:{}", port));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check("127.0.0.1", &port, "true", Some("sctp"), None, None, None).is_err());
+    assert!(check("127.0.0.1", "70000", "true", None, None, None, None).is_err());
+    // nothing is probed, so the endpoint can't be reported as blocked
+    assert!(check("127.0.0.1", &port, "false", None, Some("0"), None, None).is_err());
+    assert!(check("127.0.0.1", &port, "false", None, Some("0.0001"), None, None).is_err());
+    assert!(check("127.0.0.1", &port, "false", None, Some("-1"), None, None).is_err());
+    assert!(check("notexists.invalid", &port, "false", None, None, None, None).is_err());
+    assert!(check("notexists.invalid", &port, "false", Some("udp"), None, None, None).is_err());
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, AddrParseError};
+use std::num;
+use std::str;
+use modules::test;
+use modules::port;
+use modules::interface;
+
+const PROC_NET_ROUTE: &'static str = "/proc/net/route";
+const PROC_NET_IPV6_ROUTE: &'static str = "/proc/net/ipv6_route";
+
+/// RTF_UP in the route flags
+const RTF_UP: u32 = 0x1;
+
+#[derive(Debug)]
+pub enum RouteError {
+    Io(io::Error),
+    ParseInt(num::ParseIntError),
+    ParseBool(str::ParseBoolError),
+    AddrParse(AddrParseError)
+}
+
+impl From<io::Error> for RouteError {
+    fn from(err: io::Error) -> RouteError {
+        RouteError::Io(err)
+    }
+}
+
+impl From<num::ParseIntError> for RouteError {
+    fn from(err: num::ParseIntError) -> RouteError {
+        RouteError::ParseInt(err)
+    }
+}
+
+impl From<str::ParseBoolError> for RouteError {
+    fn from(err: str::ParseBoolError) -> RouteError {
+        RouteError::ParseBool(err)
+    }
+}
+
+impl From<AddrParseError> for RouteError {
+    fn from(err: AddrParseError) -> RouteError {
+        RouteError::AddrParse(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Route {
+    pub destination: IpAddr,
+    pub prefix: u32,
+    pub gateway: IpAddr,
+    pub interface: String
+}
+
+impl Route {
+    pub fn display(&self) -> String {
+        let mut route = format!("{}/{}", self.destination, self.prefix);
+        if !is_unspecified(&self.gateway) {
+            route = format!("{} via {}", route, self.gateway);
+        }
+        format!("{} dev {}", route, self.interface)
+    }
+}
+
+fn is_unspecified(ip: &IpAddr) -> bool {
+    match *ip {
+        IpAddr::V4(ref a) => a.octets() == [0; 4],
+        IpAddr::V6(ref a) => a.segments() == [0; 8]
+    }
+}
+
+/// returns the network address of `ip/prefix` (host bits set to 0)
+pub fn get_network(ip: &IpAddr, prefix: u32) -> IpAddr {
+    let mask_octet = |i: u32| -> u8 {
+        let bits = if prefix > i * 8 { prefix - i * 8 } else { 0 };
+        if bits >= 8 { 0xff } else { !(0xffu8 >> bits) }
+    };
+    match *ip {
+        IpAddr::V4(ref a) => {
+            let o = a.octets();
+            IpAddr::V4(Ipv4Addr::new(o[0] & mask_octet(0), o[1] & mask_octet(1),
+                                     o[2] & mask_octet(2), o[3] & mask_octet(3)))
+        },
+        IpAddr::V6(ref a) => {
+            let mut o = a.octets();
+            for i in 0..16 {
+                o[i] &= mask_octet(i as u32);
+            }
+            IpAddr::V6(Ipv6Addr::from(o))
+        }
+    }
+}
+
+/// parses `10.0.0.0/8`, `10.0.0.1` (a /32) or `default`
+/// the family of `default` is the one of the gateway when there is one
+pub fn parse_cidr(cidr: &str, gateway: Option<&IpAddr>) -> Result<(IpAddr, u32), RouteError> {
+    if cidr == "default" {
+        return match gateway {
+            Some(&IpAddr::V6(_)) => Ok((try!("::".parse::<IpAddr>()), 0)),
+            _ => Ok((try!("0.0.0.0".parse::<IpAddr>()), 0))
+        };
+    }
+    let mut parts = cidr.splitn(2, '/');
+    let ip = try!(parts.next().unwrap_or("").parse::<IpAddr>());
+    let prefix = match parts.next() {
+        Some(p) => try!(p.parse::<u32>()),
+        None => if ip.is_ipv4() { 32 } else { 128 }
+    };
+    Ok((get_network(&ip, prefix), prefix))
+}
+
+/// returns the routes of /proc/net/route (little-endian hex addresses)
+pub fn get_ipv4_routes(path: &str) -> Result<Vec<Route>, RouteError> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut routes = Vec::new();
+    for l in reader.lines().skip(1) {
+        let line = try!(l);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let flags = try!(u32::from_str_radix(fields[3], 16));
+        if flags & RTF_UP == 0 {
+            continue;
+        }
+        let mask = try!(port::parse_hex_address(fields[7]));
+        routes.push(Route {
+            destination: try!(port::parse_hex_address(fields[1])),
+            prefix: interface::get_prefix_len(&mask),
+            gateway: try!(port::parse_hex_address(fields[2])),
+            interface: fields[0].to_string()
+        });
+    }
+    Ok(routes)
+}
+
+/// parses the 32 hex digits of an address of /proc/net/ipv6_route (network order)
+pub fn parse_ipv6_hex(hex: &str) -> Option<IpAddr> {
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut octets = [0u8; 16];
+    for i in 0..16 {
+        match u8::from_str_radix(&hex[2 * i..2 * i + 2], 16) {
+            Ok(o) => octets[i] = o,
+            Err(_) => return None
+        }
+    }
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+/// returns the routes of /proc/net/ipv6_route, an empty list without IPv6 support
+pub fn get_ipv6_routes(path: &str) -> Result<Vec<Route>, RouteError> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(RouteError::Io(e))
+    };
+    let reader = io::BufReader::new(file);
+    let mut routes = Vec::new();
+    for l in reader.lines() {
+        let line = try!(l);
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let flags = try!(u32::from_str_radix(fields[8], 16));
+        if flags & RTF_UP == 0 {
+            continue;
+        }
+        match (parse_ipv6_hex(fields[0]), parse_ipv6_hex(fields[4])) {
+            (Some(destination), Some(gateway)) => routes.push(Route {
+                destination: destination,
+                prefix: try!(u32::from_str_radix(fields[1], 16)),
+                gateway: gateway,
+                interface: fields[9].to_string()
+            }),
+            _ => continue
+        }
+    }
+    Ok(routes)
+}
+
+pub fn check_route(route_path: &str,
+                   ipv6_route_path: &str,
+                   destination: &str,
+                   exists: &str,
+                   gateway: Option<&str>,
+                   interface: Option<&str>) -> Result<test::TestResult, RouteError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let test_name = "route - exists";
+    let exists_bool: bool = try!(exists.parse());
+    let gateway = match gateway {
+        Some(g) => Some(try!(g.parse::<IpAddr>())),
+        None => None
+    };
+    let (network, prefix) = try!(parse_cidr(destination, gateway.as_ref()));
+    let routes = if network.is_ipv4() {
+        try!(get_ipv4_routes(route_path))
+    }
+    else {
+        try!(get_ipv6_routes(ipv6_route_path))
+    };
+    let same_destination: Vec<&Route> = routes.iter()
+        .filter(|r| r.destination == network && r.prefix == prefix)
+        .collect();
+    let found = same_destination.iter().any(|r| {
+        gateway.map(|g| r.gateway == g).unwrap_or(true) &&
+            interface.map(|i| r.interface == i).unwrap_or(true)
+    });
+    let mut expected = format!("{}/{}", network, prefix);
+    if let Some(g) = gateway {
+        expected = format!("{} via {}", expected, g);
+    }
+    if let Some(i) = interface {
+        expected = format!("{} dev {}", expected, i);
+    }
+    expected = format!("{}: {}", expected, if exists_bool { "present" } else { "absent" });
+    let actual = if same_destination.is_empty() {
+        format!("no route to {}/{}", network, prefix)
+    }
+    else {
+        same_destination.iter().map(|r| r.display()).collect::<Vec<String>>().join(", ")
+    };
+    let message = if exists_bool { "route not found" } else { "unexpected route" };
+    test::update_test_result(found == exists_bool, test_name, &expected, &actual, message, &mut result);
+    Ok(result)
+}
+
+pub fn check(destination: &str,
+             exists: &str,
+             gateway: Option<&str>,
+             interface: Option<&str>) -> Result<test::TestResult, RouteError> {
+    check_route(PROC_NET_ROUTE, PROC_NET_IPV6_ROUTE, destination, exists, gateway, interface)
+}
+
+// TESTS
+
+#[cfg(test)]
+const ROUTE_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/route/route");
+#[cfg(test)]
+const IPV6_ROUTE_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/route/ipv6_route");
+
+#[test]
+fn parse_cidr_test_success() {
+    let (network, prefix) = parse_cidr("10.1.2.3/8", None).unwrap();
+    assert_eq!(network, "10.0.0.0".parse::<IpAddr>().unwrap());
+    assert_eq!(prefix, 8);
+    let (network, prefix) = parse_cidr("192.168.1.130/25", None).unwrap();
+    assert_eq!(network, "192.168.1.128".parse::<IpAddr>().unwrap());
+    assert_eq!(prefix, 25);
+    let (network, prefix) = parse_cidr("2001:db8:1::1/32", None).unwrap();
+    assert_eq!(network, "2001:db8::".parse::<IpAddr>().unwrap());
+    assert_eq!(prefix, 32);
+    let gateway = "fe80::1".parse::<IpAddr>().unwrap();
+    let (network, prefix) = parse_cidr("default", Some(&gateway)).unwrap();
+    assert_eq!(network, "::".parse::<IpAddr>().unwrap());
+    assert_eq!(prefix, 0);
+    assert_eq!(parse_cidr("10.0.0.1", None).unwrap().1, 32);
+    assert!(parse_cidr("10.0.0.0/a", None).is_err());
+    assert!(parse_cidr("hello", None).is_err());
+}
+
+#[test]
+fn get_ipv4_routes_test_success() {
+    let routes = get_ipv4_routes(ROUTE_FIXTURE).unwrap();
+    assert_eq!(routes.len(), 3);
+    assert_eq!(routes[0].display(), "0.0.0.0/0 via 192.168.1.1 dev eth0");
+    assert_eq!(routes[1].display(), "192.168.1.0/24 dev eth0");
+    assert_eq!(routes[2].display(), "10.0.0.0/8 via 192.168.1.1 dev eth1");
+}
+
+#[test]
+fn parse_ipv6_hex_test_success() {
+    assert_eq!(parse_ipv6_hex("20010db8000000000000000000000001"), Some("2001:db8::1".parse::<IpAddr>().unwrap()));
+    assert!(parse_ipv6_hex("20010db8").is_none());
+    assert!(parse_ipv6_hex("20010db800000000000000000000000g").is_none());
+}
+
+#[test]
+fn get_ipv6_routes_test_success() {
+    let routes = get_ipv6_routes(IPV6_ROUTE_FIXTURE).unwrap();
+    assert_eq!(routes.len(), 3);
+    assert_eq!(routes[0].display(), "::/0 via fe80::1 dev eth0");
+    assert_eq!(routes[1].display(), "2001:db8::/32 dev eth0");
+    assert_eq!(routes[2].display(), "::1/128 dev lo");
+}
+
+#[test]
+fn check_route_test_success() {
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "default", "true", Some("192.168.1.1"), Some("eth0")).unwrap();
+    assert_eq!(result.success, 1);
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "10.0.0.0/8", "true", Some("192.168.1.1"), None).unwrap();
+    assert_eq!(result.success, 1);
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "default", "true", Some("fe80::1"), None).unwrap();
+    assert_eq!(result.success, 1);
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "192.168.16.0/24", "false", None, None).unwrap();
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_route_test_error() {
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "10.0.0.0/8", "true", Some("192.168.1.254"), None).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "10.0.0.0/8 via 192.168.1.254: present");
+            assert_eq!(e.actual, "10.0.0.0/8 via 192.168.1.1 dev eth1");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "172.16.0.0/12", "true", None, None).unwrap();
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "no route to 172.16.0.0/12"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_route(ROUTE_FIXTURE, IPV6_ROUTE_FIXTURE, "default", "true", Some("gw"), None).is_err());
+}
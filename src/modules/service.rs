@@ -0,0 +1,425 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::str;
+use distrib;
+use modules::test;
+use util;
+
+/// unit directories, by precedence
+pub const SYSTEMD_UNIT_DIRS: [&'static str; 4] = ["etc/systemd/system", "run/systemd/system",
+                                               "lib/systemd/system", "usr/lib/systemd/system"];
+
+#[derive(Debug)]
+pub enum ServiceError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    /// the active and running states can't be read under another root
+    Root(String)
+}
+
+impl From<io::Error> for ServiceError {
+    fn from(err: io::Error) -> ServiceError {
+        ServiceError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for ServiceError {
+    fn from(err: str::ParseBoolError) -> ServiceError {
+        ServiceError::ParseBool(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub status: Option<i32>,
+    pub stdout: String
+}
+
+/// runs the commands querying the service manager, so tests can use a fake one
+pub trait CommandRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput, io::Error>;
+}
+
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput, io::Error> {
+        let output = try!(Command::new(program).args(args).output());
+        Ok(CommandOutput {
+            status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned()
+        })
+    }
+}
+
+/// `nginx` is `nginx.service` for systemd
+pub fn get_unit_name(name: &str) -> String {
+    if name.contains('.') {
+        name.to_string()
+    }
+    else {
+        format!("{}.service", name)
+    }
+}
+
+/// a unit is enabled when it is linked in a .wants or .requires directory
+/// vendor directories are included, for units enabled by the distribution
+pub fn is_enabled_systemd(root: &str, unit: &str) -> Result<bool, io::Error> {
+    for dir in SYSTEMD_UNIT_DIRS.iter() {
+        let entries = match fs::read_dir(util::join_root(root, dir)) {
+            Ok(e) => e,
+            Err(_) => continue
+        };
+        for entry in entries {
+            let path = try!(entry).path();
+            let is_dependency_dir = path.file_name()
+                .map(|n| {
+                    let n = n.to_string_lossy();
+                    n.ends_with(".wants") || n.ends_with(".requires")
+                })
+                .unwrap_or(false);
+            if is_dependency_dir && fs::symlink_metadata(path.join(unit)).is_ok() {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// a unit is masked when it is linked to /dev/null in /etc or /run
+pub fn is_masked_systemd(root: &str, unit: &str) -> bool {
+    SYSTEMD_UNIT_DIRS[0..2].iter().any(|dir| {
+        let path = util::join_root(root, &format!("{}/{}", dir, unit));
+        match fs::read_link(Path::new(&path)) {
+            Ok(target) => target == Path::new("/dev/null"),
+            Err(_) => false
+        }
+    })
+}
+
+/// returns (ActiveState, SubState) of `systemctl show`
+pub fn get_runtime_state_systemd<R: CommandRunner>(runner: &R, unit: &str) -> Result<(String, String), io::Error> {
+    let output = try!(runner.run("systemctl", &["show", unit, "--property=ActiveState,SubState"]));
+    let mut active_state = "".to_string();
+    let mut sub_state = "".to_string();
+    for line in output.stdout.lines() {
+        let mut parts = line.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("ActiveState"), Some(v)) => active_state = v.trim().to_string(),
+            (Some("SubState"), Some(v)) => sub_state = v.trim().to_string(),
+            _ => {}
+        }
+    }
+    Ok((active_state, sub_state))
+}
+
+/// rc?.d directories of the multi-user runlevels
+const SYSV_RUNLEVEL_DIRS: [&'static str; 4] = ["etc/rc2.d", "etc/rc3.d", "etc/rc4.d", "etc/rc5.d"];
+
+/// `nginx.service` is `nginx` for sysv and openrc
+pub fn get_script_name(name: &str) -> String {
+    name.trim_end_matches(".service").to_string()
+}
+
+/// a sysv service is enabled when a multi-user runlevel has a S??<service> link
+pub fn is_enabled_sysv(root: &str, service: &str) -> Result<bool, io::Error> {
+    for dir in SYSV_RUNLEVEL_DIRS.iter() {
+        let entries = match fs::read_dir(util::join_root(root, dir)) {
+            Ok(e) => e,
+            Err(_) => continue
+        };
+        for entry in entries {
+            let file_name = try!(entry).file_name().to_string_lossy().into_owned();
+            if file_name.starts_with('S') && file_name.len() == service.len() + 3 && file_name.ends_with(service) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// an openrc service is enabled when it is added to a runlevel
+pub fn is_enabled_openrc(root: &str, service: &str) -> Result<bool, io::Error> {
+    let entries = match fs::read_dir(util::join_root(root, "etc/runlevels")) {
+        Ok(e) => e,
+        Err(_) => return Ok(false)
+    };
+    for entry in entries {
+        let path = try!(entry).path();
+        if fs::symlink_metadata(path.join(service)).is_ok() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// returns the exit code of the status command of a sysv or openrc service
+/// (0 when the service is running), None if the script is missing or killed
+pub fn get_status_code<R: CommandRunner>(runner: &R, init: &str, service: &str) -> Result<Option<i32>, io::Error> {
+    let output = if init == "openrc" {
+        runner.run("rc-service", &[service, "status"])
+    }
+    else {
+        runner.run(&format!("/etc/init.d/{}", service), &["status"])
+    };
+    match output {
+        Ok(o) => Ok(o.status),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+fn check_state(test_name: &str, expected: bool, actual: bool, actual_text: &str, result: &mut test::TestResult) {
+    let message = format!("{} should be {}", test_name, expected);
+    test::update_test_result(expected == actual, test_name, &expected.to_string(), actual_text, &message, result);
+}
+
+fn init_system_not_found(name: &str, init: &str, result: &mut test::TestResult) {
+    let error = test::UnitError {
+        test: "service".to_string(),
+        expected: format!("name: {}", name),
+        actual: format!("init system not supported: {}", init),
+        message: "lothaire failed to determine your init system".to_string(),
+    };
+    result.error += 1;
+    result.summary.push(test::UnitResult::from(error));
+}
+
+pub fn check_service<R: CommandRunner>(runner: &R,
+                                       init: &str,
+                                       root: &str,
+                                       name: &str,
+                                       enabled: Option<&str>,
+                                       active: Option<&str>,
+                                       running: Option<&str>,
+                                       masked: Option<&str>) -> Result<test::TestResult, ServiceError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let enabled = try!(util::parse_bool(enabled));
+    let active = try!(util::parse_bool(active));
+    let running = try!(util::parse_bool(running));
+    let masked = try!(util::parse_bool(masked));
+    let service = match init {
+        "systemd" => get_unit_name(name),
+        "sysv" | "openrc" => get_script_name(name),
+        _ => {
+            init_system_not_found(name, init, &mut result);
+            return Ok(result);
+        }
+    };
+    if let Some(enabled) = enabled {
+        let is_enabled = try!(match init {
+            "systemd" => is_enabled_systemd(root, &service),
+            "sysv" => is_enabled_sysv(root, &service),
+            _ => is_enabled_openrc(root, &service)
+        });
+        check_state("service - enabled", enabled, is_enabled, &is_enabled.to_string(), &mut result);
+    }
+    if let Some(masked) = masked {
+        // masking only exists with systemd
+        let is_masked = init == "systemd" && is_masked_systemd(root, &service);
+        check_state("service - masked", masked, is_masked, &is_masked.to_string(), &mut result);
+    }
+    if active.is_none() && running.is_none() {
+        return Ok(result);
+    }
+    let (is_active, active_text, is_running, running_text) = if init == "systemd" {
+        let (active_state, sub_state) = try!(get_runtime_state_systemd(runner, &service));
+        (active_state == "active", active_state, sub_state == "running", sub_state)
+    }
+    else {
+        // sysv and openrc only know if the service is started
+        let status = try!(get_status_code(runner, init, &service));
+        let text = match status {
+            Some(code) => format!("status exit code {}", code),
+            None => "status unavailable".to_string()
+        };
+        (status == Some(0), text.to_string(), status == Some(0), text)
+    };
+    if let Some(active) = active {
+        check_state("service - active", active, is_active, &active_text, &mut result);
+    }
+    if let Some(running) = running {
+        check_state("service - running", running, is_running, &running_text, &mut result);
+    }
+    Ok(result)
+}
+
+/// the init system is detected unless `init` is given (systemd, sysv or openrc)
+/// `root` only applies to the enabled and masked checks, the active and running states
+/// are always the ones of the running system, so they are rejected with another root
+pub fn check(name: &str,
+             enabled: Option<&str>,
+             active: Option<&str>,
+             running: Option<&str>,
+             masked: Option<&str>,
+             root: Option<&str>,
+             init: Option<&str>) -> Result<test::TestResult, ServiceError> {
+    let init = match init {
+        Some(i) => i.to_string(),
+        None => distrib::get_init_system()
+    };
+    let root = root.unwrap_or("/");
+    if root != "/" && (active.is_some() || running.is_some()) {
+        return Err(ServiceError::Root(root.to_string()));
+    }
+    check_service(&SystemRunner, &init, root, name, enabled, active, running, masked)
+}
+
+// TESTS
+
+#[cfg(test)]
+const SYSTEMD_ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/service/systemd");
+#[cfg(test)]
+const SYSV_ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/service/sysv");
+#[cfg(test)]
+const OPENRC_ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/service/openrc");
+
+/// returns the same output for every command, and records the commands
+#[cfg(test)]
+pub struct FakeRunner {
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub commands: ::std::cell::RefCell<Vec<String>>
+}
+
+#[cfg(test)]
+impl FakeRunner {
+    pub fn new(status: Option<i32>, stdout: &str) -> FakeRunner {
+        FakeRunner {
+            status: status,
+            stdout: stdout.to_string(),
+            commands: ::std::cell::RefCell::new(Vec::new())
+        }
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for FakeRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput, io::Error> {
+        self.commands.borrow_mut().push(format!("{} {}", program, args.join(" ")));
+        Ok(CommandOutput {
+            status: self.status,
+            stdout: self.stdout.to_string()
+        })
+    }
+}
+
+#[test]
+fn get_unit_name_test_success() {
+    assert_eq!(get_unit_name("nginx"), "nginx.service");
+    assert_eq!(get_unit_name("backup.timer"), "backup.timer");
+}
+
+#[test]
+fn is_enabled_systemd_test_success() {
+    assert!(is_enabled_systemd(SYSTEMD_ROOT_FIXTURE, "nginx.service").unwrap());
+    assert!(is_enabled_systemd(SYSTEMD_ROOT_FIXTURE, "getty@tty1.service").unwrap());
+    assert!(!is_enabled_systemd(SYSTEMD_ROOT_FIXTURE, "cron.service").unwrap());
+    assert!(!is_enabled_systemd("/notexists", "cron.service").unwrap());
+}
+
+#[test]
+fn is_masked_systemd_test_success() {
+    assert!(is_masked_systemd(SYSTEMD_ROOT_FIXTURE, "telnet.service"));
+    assert!(!is_masked_systemd(SYSTEMD_ROOT_FIXTURE, "nginx.service"));
+}
+
+#[test]
+fn get_runtime_state_systemd_test_success() {
+    let runner = FakeRunner::new(Some(0), "ActiveState=active\nSubState=running\n");
+    let state = get_runtime_state_systemd(&runner, "nginx.service").unwrap();
+    assert_eq!(state, ("active".to_string(), "running".to_string()));
+    assert_eq!(*runner.commands.borrow(), vec!["systemctl show nginx.service --property=ActiveState,SubState"]);
+}
+
+#[test]
+fn check_service_test_success() {
+    let runner = FakeRunner::new(Some(0), "ActiveState=active\nSubState=running\n");
+    let result = check_service(&runner, "systemd", SYSTEMD_ROOT_FIXTURE, "nginx", Some("true"), Some("true"),
+                               Some("true"), Some("false")).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    let result = check_service(&runner, "systemd", SYSTEMD_ROOT_FIXTURE, "telnet", Some("false"), None,
+                               None, Some("true")).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_service_test_error() {
+    let runner = FakeRunner::new(Some(0), "ActiveState=failed\nSubState=failed\n");
+    let result = check_service(&runner, "systemd", SYSTEMD_ROOT_FIXTURE, "cron", Some("true"), Some("true"),
+                               Some("true"), None).unwrap();
+    assert_eq!(result.success, 0);
+    assert_eq!(result.error, 3);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "service - active");
+            assert_eq!(e.expected, "true");
+            assert_eq!(e.actual, "failed");
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_service(&runner, "systemd", SYSTEMD_ROOT_FIXTURE, "cron", Some("yes"), None, None, None).is_err());
+    assert!(check("cron", None, Some("true"), None, None, Some(SYSTEMD_ROOT_FIXTURE), Some("systemd")).is_err());
+    assert!(check("cron", None, None, Some("false"), None, Some(SYSTEMD_ROOT_FIXTURE), Some("systemd")).is_err());
+}
+
+#[test]
+fn is_enabled_sysv_test_success() {
+    assert!(is_enabled_sysv(SYSV_ROOT_FIXTURE, "ssh").unwrap());
+    assert!(!is_enabled_sysv(SYSV_ROOT_FIXTURE, "telnet").unwrap());
+    assert!(!is_enabled_sysv(SYSV_ROOT_FIXTURE, "sh").unwrap());
+}
+
+#[test]
+fn is_enabled_openrc_test_success() {
+    assert!(is_enabled_openrc(OPENRC_ROOT_FIXTURE, "sshd").unwrap());
+    assert!(is_enabled_openrc(OPENRC_ROOT_FIXTURE, "hostname").unwrap());
+    assert!(!is_enabled_openrc(OPENRC_ROOT_FIXTURE, "crond").unwrap());
+}
+
+#[test]
+fn check_service_sysv_test_success() {
+    let runner = FakeRunner::new(Some(0), "sshd is running.\n");
+    let result = check_service(&runner, "sysv", SYSV_ROOT_FIXTURE, "ssh.service", Some("true"), Some("true"),
+                               Some("true"), Some("false")).unwrap();
+    assert_eq!(result.success, 4);
+    assert_eq!(result.error, 0);
+    assert_eq!(*runner.commands.borrow(), vec!["/etc/init.d/ssh status"]);
+    let runner = FakeRunner::new(Some(3), "telnet is not running.\n");
+    let result = check_service(&runner, "sysv", SYSV_ROOT_FIXTURE, "telnet", Some("true"), None,
+                               Some("true"), None).unwrap();
+    assert_eq!(result.error, 2);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "status exit code 3"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+}
+
+#[test]
+fn check_service_openrc_test_success() {
+    let runner = FakeRunner::new(Some(3), " * status: stopped\n");
+    let result = check_service(&runner, "openrc", OPENRC_ROOT_FIXTURE, "crond", Some("false"), Some("false"),
+                               None, None).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    assert_eq!(*runner.commands.borrow(), vec!["rc-service crond status"]);
+}
+
+#[test]
+fn check_service_unknown_init_test_error() {
+    let runner = FakeRunner::new(Some(0), "");
+    let result = check_service(&runner, "upstart", SYSV_ROOT_FIXTURE, "ssh", Some("true"), None, None, None).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "init system not supported: upstart"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use modules::test;
+use util;
+
+const SSHD_CONFIG: &'static str = "/etc/ssh/sshd_config";
+/// same limit as OpenSSH
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum SshdConfigError {
+    Io(io::Error),
+    IncludeDepth(String),
+    Setting(String)
+}
+
+impl From<io::Error> for SshdConfigError {
+    fn from(err: io::Error) -> SshdConfigError {
+        SshdConfigError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Directive {
+    /// lowercased, keywords are case-insensitive
+    pub keyword: String,
+    pub value: String,
+    /// the Match line of the block containing the directive, None for global directives
+    pub match_block: Option<String>,
+    pub file: String,
+    pub line: usize
+}
+
+impl Directive {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+/// splits `Keyword value`, `Keyword=value` or `Keyword = value`
+pub fn parse_line(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(line.len());
+    let keyword = line[..end].to_lowercase();
+    let value = line[end..].trim_start().trim_start_matches('=').trim();
+    let value = value.trim_matches('"');
+    Some((keyword, value.to_string()))
+}
+
+/// expands an Include pattern, relative paths are relative to the directory of the main file
+/// (/etc/ssh), wildcards are only supported in the file name
+pub fn expand_include(base_dir: &str, pattern: &str) -> Result<Vec<String>, io::Error> {
+    let path = if pattern.starts_with('/') { pattern.to_string() } else { format!("{}/{}", base_dir, pattern) };
+    if !path.contains('*') && !path.contains('?') {
+        return Ok(if Path::new(&path).exists() { vec![path] } else { Vec::new() });
+    }
+    let (dir, file_pattern) = match path.rfind('/') {
+        Some(i) => (path[..i].to_string(), path[i + 1..].to_string()),
+        None => (".".to_string(), path.clone())
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(e) => e,
+        Err(_) => return Ok(Vec::new())
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let name = try!(entry).file_name().to_string_lossy().into_owned();
+        if util::wildcard_match(file_pattern.as_bytes(), name.as_bytes()) {
+            files.push(format!("{}/{}", dir, name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// a Match block started in an included file ends with that file
+fn parse_file(path: &str,
+              base_dir: &str,
+              depth: usize,
+              parent_match_block: &Option<String>,
+              directives: &mut Vec<Directive>) -> Result<(), SshdConfigError> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(SshdConfigError::IncludeDepth(path.to_string()));
+    }
+    let mut match_block = parent_match_block.clone();
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let (keyword, value) = match parse_line(&line) {
+            Some(d) => d,
+            None => continue
+        };
+        match keyword.as_str() {
+            "include" => {
+                for pattern in value.split_whitespace() {
+                    for included in try!(expand_include(base_dir, pattern)) {
+                        try!(parse_file(&included, base_dir, depth + 1, &match_block, directives));
+                    }
+                }
+            }
+            "match" => {
+                match_block = if value.to_lowercase() == "all" { None } else { Some(value) };
+            }
+            _ => directives.push(Directive {
+                keyword: keyword,
+                value: value,
+                match_block: match_block.clone(),
+                file: path.to_string(),
+                line: i + 1
+            })
+        }
+    }
+    Ok(())
+}
+
+/// returns the directives of the file and of its includes, in the order sshd reads them
+pub fn get_directives(path: &str) -> Result<Vec<Directive>, SshdConfigError> {
+    let base_dir = Path::new(path).parent().map_or(".".to_string(), |p| p.to_string_lossy().into_owned());
+    let mut directives = Vec::new();
+    try!(parse_file(path, &base_dir, 0, &None, &mut directives));
+    Ok(directives)
+}
+
+/// the first value wins. For a Match block (selected by its Match line, like `User deploy`,
+/// criteria are not evaluated), its values override the global ones.
+pub fn get_effective<'a>(directives: &'a Vec<Directive>, keyword: &str, match_block: Option<&str>) -> Option<&'a Directive> {
+    let keyword = keyword.to_lowercase();
+    let in_block = match_block.and_then(|m| {
+        directives.iter().find(|d| {
+            d.keyword == keyword && d.match_block.as_ref().map_or(false, |b| b.to_lowercase() == m.to_lowercase())
+        })
+    });
+    in_block.or_else(|| directives.iter().find(|d| d.keyword == keyword && d.match_block.is_none()))
+}
+
+fn split_setting(setting: &str) -> Result<(&str, &str), SshdConfigError> {
+    match setting.find('=') {
+        Some(i) if i > 0 => Ok((setting[..i].trim(), setting[i + 1..].trim())),
+        _ => Err(SshdConfigError::Setting(setting.to_string()))
+    }
+}
+
+fn describe(keyword: &str, directive: Option<&Directive>) -> String {
+    match directive {
+        Some(d) => format!("{} {} ({})", keyword, d.value, d.location()),
+        None => format!("{} not set", keyword)
+    }
+}
+
+/// unset keywords fail, their OpenSSH defaults are not known
+fn check_setting(keyword: &str, value: &str, directive: Option<&Directive>, result: &mut test::TestResult) {
+    let test_name = "sshd_config - setting";
+    let condition = directive.map_or(false, |d| d.value.to_lowercase() == value.to_lowercase());
+    test::update_test_result(condition, test_name, &format!("{} {}", keyword, value), &describe(keyword, directive),
+                             "incorrect setting", result);
+}
+
+/// every algorithm of the list must be allowed, lists relative to the defaults (+, -, ^) fail
+fn check_allowed(keyword: &str, allowed: &str, directive: Option<&Directive>, result: &mut test::TestResult) {
+    let test_name = "sshd_config - allowed";
+    let allowed_list: Vec<&str> = allowed.split(',').collect();
+    let condition = directive.map_or(false, |d| {
+        !d.value.starts_with(|c| c == '+' || c == '-' || c == '^')
+            && d.value.split(',').all(|v| allowed_list.contains(&v))
+    });
+    test::update_test_result(condition, test_name, &format!("{} {}", keyword, allowed), &describe(keyword, directive),
+                             "algorithms not allowed", result);
+}
+
+pub fn check_sshd_config(path: &str,
+                         settings: &[&str],
+                         allowed: &[&str],
+                         match_block: Option<&str>) -> Result<test::TestResult, SshdConfigError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let directives = try!(get_directives(path));
+    for setting in settings {
+        let (keyword, value) = try!(split_setting(setting));
+        check_setting(keyword, value, get_effective(&directives, keyword, match_block), &mut result);
+    }
+    for setting in allowed {
+        let (keyword, value) = try!(split_setting(setting));
+        check_allowed(keyword, value, get_effective(&directives, keyword, match_block), &mut result);
+    }
+    Ok(result)
+}
+
+pub fn check(settings: &[&str], allowed: &[&str], match_block: Option<&str>) -> Result<test::TestResult, SshdConfigError> {
+    check_sshd_config(SSHD_CONFIG, settings, allowed, match_block)
+}
+
+// TESTS
+
+#[cfg(test)]
+const SSHD_CONFIG_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/sshd_config/sshd_config");
+#[cfg(test)]
+const SSHD_CONFIG_DIR_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/sshd_config");
+
+#[test]
+fn parse_line_test_success() {
+    assert_eq!(parse_line("PermitRootLogin no"), Some(("permitrootlogin".to_string(), "no".to_string())));
+    assert_eq!(parse_line("  MACs=hmac-sha2-512 "), Some(("macs".to_string(), "hmac-sha2-512".to_string())));
+    assert_eq!(parse_line("Banner = \"/etc/issue\""), Some(("banner".to_string(), "/etc/issue".to_string())));
+    assert_eq!(parse_line("Subsystem\tsftp\t/usr/lib/sftp"), Some(("subsystem".to_string(), "sftp\t/usr/lib/sftp".to_string())));
+    assert!(parse_line("# PermitRootLogin yes").is_none());
+    assert!(parse_line("   ").is_none());
+}
+
+#[test]
+fn expand_include_test_success() {
+    let files = expand_include(SSHD_CONFIG_DIR_FIXTURE, "sshd_config.d/*.conf").unwrap();
+    assert_eq!(files, vec![format!("{}/sshd_config.d/10-hardening.conf", SSHD_CONFIG_DIR_FIXTURE),
+                           format!("{}/sshd_config.d/50-cloud-init.conf", SSHD_CONFIG_DIR_FIXTURE)]);
+    let files = expand_include(SSHD_CONFIG_DIR_FIXTURE, "sshd_config.d/?0-hard*").unwrap();
+    assert_eq!(files.len(), 1);
+    assert!(expand_include(SSHD_CONFIG_DIR_FIXTURE, "notexists.d/*.conf").unwrap().is_empty());
+    assert!(expand_include(SSHD_CONFIG_DIR_FIXTURE, "notexists.conf").unwrap().is_empty());
+}
+
+#[test]
+fn get_effective_test_success() {
+    let directives = get_directives(SSHD_CONFIG_FIXTURE).unwrap();
+    let directive = get_effective(&directives, "PermitRootLogin", None).unwrap();
+    assert_eq!(directive.value, "no");
+    assert_eq!(directive.location(), format!("{}/sshd_config.d/10-hardening.conf:1", SSHD_CONFIG_DIR_FIXTURE));
+    let directive = get_effective(&directives, "passwordauthentication", None).unwrap();
+    assert_eq!(directive.location(), format!("{}/sshd_config.d/50-cloud-init.conf:1", SSHD_CONFIG_DIR_FIXTURE));
+    let directive = get_effective(&directives, "PasswordAuthentication", Some("address 10.0.0.0/8")).unwrap();
+    assert_eq!(directive.value, "yes");
+    assert_eq!(directive.line, 19);
+    let directive = get_effective(&directives, "PermitRootLogin", Some("User deploy")).unwrap();
+    assert_eq!(directive.value, "no");
+    assert!(get_effective(&directives, "AllowTcpForwarding", None).is_none());
+}
+
+#[test]
+fn check_sshd_config_test_success() {
+    let result = check_sshd_config(SSHD_CONFIG_FIXTURE, &["PermitRootLogin=no", "passwordauthentication=No"],
+                                   &["KexAlgorithms=curve25519-sha256,curve25519-sha256@libssh.org,sntrup761x25519-sha512"],
+                                   None).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check_sshd_config(SSHD_CONFIG_FIXTURE, &["AllowTcpForwarding=yes", "X11Forwarding=yes"], &[],
+                                   Some("User deploy")).unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_sshd_config_test_error() {
+    let result = check_sshd_config(SSHD_CONFIG_FIXTURE, &["PasswordAuthentication=no", "PermitEmptyPasswords=no"],
+                                   &["Ciphers=aes256-gcm@openssh.com,chacha20-poly1305@openssh.com"],
+                                   Some("Address 10.0.0.0/8")).unwrap();
+    assert_eq!(result.error, 3);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "PasswordAuthentication no");
+            assert_eq!(e.actual, format!("PasswordAuthentication yes ({}:19)", SSHD_CONFIG_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "PermitEmptyPasswords not set"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_sshd_config(SSHD_CONFIG_FIXTURE, &["PermitRootLogin"], &[], None).is_err());
+    assert!(check_sshd_config("/notexists", &[], &[], None).is_err());
+}
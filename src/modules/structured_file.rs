@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::str;
+use regex::{self, Regex};
+use rustc_serialize::json::{self, Json};
+use toml;
+use yaml_rust::{Yaml, YamlLoader, ScanError};
+use modules::test;
+use util;
+
+#[derive(Debug)]
+pub enum StructuredFileError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    Regex(regex::Error),
+    Json(json::ParserError),
+    Yaml(ScanError),
+    Toml(String),
+    Format(String),
+    Query(String)
+}
+
+impl From<io::Error> for StructuredFileError {
+    fn from(err: io::Error) -> StructuredFileError {
+        StructuredFileError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for StructuredFileError {
+    fn from(err: str::ParseBoolError) -> StructuredFileError {
+        StructuredFileError::ParseBool(err)
+    }
+}
+
+impl From<regex::Error> for StructuredFileError {
+    fn from(err: regex::Error) -> StructuredFileError {
+        StructuredFileError::Regex(err)
+    }
+}
+
+impl From<json::ParserError> for StructuredFileError {
+    fn from(err: json::ParserError) -> StructuredFileError {
+        StructuredFileError::Json(err)
+    }
+}
+
+impl From<ScanError> for StructuredFileError {
+    fn from(err: ScanError) -> StructuredFileError {
+        StructuredFileError::Yaml(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Key(String),
+    Index(usize),
+    /// every element of an array, every value of an object
+    Wildcard
+}
+
+/// `.server.port`, `.upstreams[0].host`, `.upstreams[*].host`, `.["log.level"]` for keys
+/// containing dots. `.` alone is the whole document. The leading dot is optional.
+pub fn parse_query(query: &str) -> Result<Vec<Step>, StructuredFileError> {
+    let error = || StructuredFileError::Query(query.to_string());
+    let mut steps = Vec::new();
+    let mut rest = if query.starts_with('.') { &query[1..] } else { query };
+    while !rest.is_empty() {
+        let end = if rest.starts_with('[') {
+            let end = try!(rest.find(']').ok_or_else(&error));
+            let index = rest[1..end].trim();
+            steps.push(if index == "*" {
+                Step::Wildcard
+            }
+            else if index.len() > 1 && index.starts_with('"') && index.ends_with('"') {
+                Step::Key(index[1..index.len() - 1].to_string())
+            }
+            else {
+                Step::Index(try!(index.parse::<usize>().map_err(|_| error())))
+            });
+            end + 1
+        }
+        else {
+            let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+            match &rest[..end] {
+                "" => return Err(error()),
+                "*" => steps.push(Step::Wildcard),
+                key => steps.push(Step::Key(key.to_string()))
+            }
+            end
+        };
+        rest = &rest[end..];
+        if rest.starts_with('.') {
+            rest = &rest[1..];
+            if rest.is_empty() {
+                return Err(error());
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// the values matched by the query, several with wildcards, none when a step doesn't exist.
+/// A numeric key indexes an array (`upstreams.1.host`), like the dotted paths of http.
+pub fn evaluate<'a>(document: &'a Json, steps: &[Step]) -> Vec<&'a Json> {
+    let mut current = vec![document];
+    for step in steps {
+        let mut next = Vec::new();
+        for value in current {
+            match (step, value) {
+                (&Step::Key(ref k), &Json::Object(ref o)) => next.extend(o.get(k)),
+                (&Step::Index(i), &Json::Array(ref a)) => next.extend(a.get(i)),
+                (&Step::Key(ref k), &Json::Array(ref a)) => next.extend(k.parse::<usize>().ok().and_then(|i| a.get(i))),
+                (&Step::Wildcard, &Json::Array(ref a)) => next.extend(a.iter()),
+                (&Step::Wildcard, &Json::Object(ref o)) => next.extend(o.values()),
+                _ => {}
+            }
+        }
+        current = next;
+    }
+    current
+}
+
+/// keys of YAML mappings are not always strings (`80: http`), they are converted like scalars
+fn yaml_key(key: &Yaml) -> Option<String> {
+    match *key {
+        Yaml::String(ref s) | Yaml::Real(ref s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        Yaml::Null => Some("null".to_string()),
+        _ => None
+    }
+}
+
+pub fn yaml_to_json(yaml: &Yaml) -> Json {
+    match *yaml {
+        Yaml::Real(ref s) => s.parse::<f64>().map(Json::F64).unwrap_or(Json::String(s.clone())),
+        Yaml::Integer(i) => Json::I64(i),
+        Yaml::String(ref s) => Json::String(s.clone()),
+        Yaml::Boolean(b) => Json::Boolean(b),
+        Yaml::Array(ref a) => Json::Array(a.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(ref h) => {
+            let mut object = BTreeMap::new();
+            for (k, v) in h {
+                if let Some(key) = yaml_key(k) {
+                    object.insert(key, yaml_to_json(v));
+                }
+            }
+            Json::Object(object)
+        }
+        _ => Json::Null
+    }
+}
+
+/// datetimes are kept as strings
+pub fn toml_to_json(value: &toml::Value) -> Json {
+    match *value {
+        toml::Value::String(ref s) | toml::Value::Datetime(ref s) => Json::String(s.clone()),
+        toml::Value::Integer(i) => Json::I64(i),
+        toml::Value::Float(f) => Json::F64(f),
+        toml::Value::Boolean(b) => Json::Boolean(b),
+        toml::Value::Array(ref a) => Json::Array(a.iter().map(toml_to_json).collect()),
+        toml::Value::Table(ref t) => Json::Object(t.iter().map(|(k, v)| (k.clone(), toml_to_json(v))).collect())
+    }
+}
+
+/// the format given, or the one of the extension of the file
+pub fn get_format(path: &str, format: Option<&str>) -> Result<String, StructuredFileError> {
+    let format = format.unwrap_or_else(|| path.rsplit('.').next().unwrap_or(""));
+    match format {
+        "json" => Ok("json".to_string()),
+        "yaml" | "yml" => Ok("yaml".to_string()),
+        "toml" => Ok("toml".to_string()),
+        f => Err(StructuredFileError::Format(f.to_string()))
+    }
+}
+
+/// YAML and TOML documents are converted to JSON, only the first document of a YAML stream is read
+pub fn parse_document(content: &str, format: &str) -> Result<Json, StructuredFileError> {
+    match format {
+        "json" => Ok(try!(Json::from_str(content))),
+        "yaml" => Ok(try!(YamlLoader::load_from_str(content)).first().map_or(Json::Null, yaml_to_json)),
+        "toml" => {
+            let mut parser = toml::Parser::new(content);
+            match parser.parse() {
+                Some(table) => Ok(toml_to_json(&toml::Value::Table(table))),
+                None => {
+                    let errors: Vec<String> = parser.errors.iter().map(|e| {
+                        let (line, col) = parser.to_linecol(e.lo);
+                        format!("{}:{}: {}", line + 1, col + 1, e.desc)
+                    }).collect();
+                    Err(StructuredFileError::Toml(errors.join(", ")))
+                }
+            }
+        }
+        f => Err(StructuredFileError::Format(f.to_string()))
+    }
+}
+
+/// formats: json, yaml or toml, from the extension of the file by default
+pub fn check(path: &str,
+             format: Option<&str>,
+             query: &str,
+             exists: Option<&str>,
+             value: Option<&str>,
+             regex: Option<&str>) -> Result<test::TestResult, StructuredFileError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let exists = try!(util::parse_bool(exists)).unwrap_or(true);
+    let regex = match regex {
+        Some(r) => Some(try!(Regex::new(r))),
+        None => None
+    };
+    let steps = try!(parse_query(query));
+    let format = try!(get_format(path, format));
+    let mut content = String::new();
+    try!(try!(fs::File::open(path)).read_to_string(&mut content));
+    let document = try!(parse_document(&content, &format));
+    let values: Vec<String> = evaluate(&document, &steps).into_iter().map(util::json_to_string).collect();
+    test::check_exists(&values.first(), exists, &mut result, "structured_file - exists".to_string());
+    if values.is_empty() {
+        return Ok(result);
+    }
+    // with wildcards, every matched value is tested
+    let actual = format!("{}={} ({})", query, values.join(", "), path);
+    value.map(|v| {
+        test::update_test_result(values.iter().all(|a| a == v), "structured_file - value", &format!("{}={}", query, v),
+                                 &actual, "incorrect value", &mut result);
+    });
+    regex.map(|r| {
+        test::update_test_result(values.iter().all(|a| r.is_match(a)), "structured_file - regex", r.as_str(), &actual,
+                                 "value doesn't match", &mut result);
+    });
+    Ok(result)
+}
+
+// TESTS
+
+#[cfg(test)]
+const FIXTURES: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/structured_file");
+
+#[cfg(test)]
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+#[cfg(test)]
+fn load_fixture(name: &str) -> Json {
+    let path = fixture(name);
+    let mut content = String::new();
+    fs::File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+    parse_document(&content, &get_format(&path, None).unwrap()).unwrap()
+}
+
+#[test]
+fn parse_query_test_success() {
+    assert_eq!(parse_query(".server.port").unwrap(), vec![Step::Key("server".to_string()), Step::Key("port".to_string())]);
+    assert_eq!(parse_query("upstreams[*].host").unwrap(),
+               vec![Step::Key("upstreams".to_string()), Step::Wildcard, Step::Key("host".to_string())]);
+    assert_eq!(parse_query(".upstreams[1]").unwrap(), vec![Step::Key("upstreams".to_string()), Step::Index(1)]);
+    assert_eq!(parse_query(".[\"log.level\"]").unwrap(), vec![Step::Key("log.level".to_string())]);
+    assert_eq!(parse_query(".server.*").unwrap(), vec![Step::Key("server".to_string()), Step::Wildcard]);
+    assert!(parse_query(".").unwrap().is_empty());
+}
+
+#[test]
+fn parse_query_test_error() {
+    assert!(parse_query(".server..port").is_err());
+    assert!(parse_query(".server.").is_err());
+    assert!(parse_query(".upstreams[a]").is_err());
+    assert!(parse_query(".upstreams[0").is_err());
+}
+
+#[test]
+fn evaluate_test_success() {
+    for name in &["app.json", "app.yaml", "app.toml"] {
+        let document = load_fixture(name);
+        let get = |query: &str| -> Vec<String> {
+            evaluate(&document, &parse_query(query).unwrap()).into_iter().map(util::json_to_string).collect()
+        };
+        assert_eq!(get(".server.port"), vec!["8080"]);
+        assert_eq!(get(".server.timeout"), vec!["2.5"]);
+        assert_eq!(get(".debug"), vec!["false"]);
+        assert_eq!(get(".upstreams[*].host"), vec!["10.0.0.11", "10.0.0.12"]);
+        assert_eq!(get(".upstreams[1].port"), vec!["9000"]);
+        assert_eq!(get("upstreams.1.port"), vec!["9000"]);
+        assert_eq!(get(".features"), vec!["[\"metrics\",\"tracing\"]"]);
+        assert_eq!(get(".[\"log.level\"]"), vec!["info"]);
+        assert!(get(".server.tls").is_empty());
+        assert!(get(".upstreams[2].host").is_empty());
+        assert!(get(".name.first").is_empty());
+    }
+}
+
+#[test]
+fn get_format_test_success() {
+    assert_eq!(get_format("/etc/app/config.yml", None).unwrap(), "yaml");
+    assert_eq!(get_format("/etc/app/config.json", None).unwrap(), "json");
+    assert_eq!(get_format("/etc/app/config", Some("toml")).unwrap(), "toml");
+    assert!(get_format("/etc/app/config.ini", None).is_err());
+    assert!(get_format("/etc/app/config.json", Some("xml")).is_err());
+}
+
+#[test]
+fn check_test_success() {
+    let result = check(&fixture("app.yaml"), None, ".server.port", None, Some("8080"), Some("^[0-9]+$")).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check(&fixture("app.toml"), None, ".upstreams[*].host", None, None, Some("^10\\.0\\.0\\.")).unwrap();
+    assert_eq!(result.success, 2);
+    let result = check(&fixture("app.json"), Some("json"), ".server.tls", Some("false"), None, None).unwrap();
+    assert_eq!(result.success, 1);
+    let result = check(&fixture("app.json"), None, ".upstreams[*].port", Some("true"), Some("9000"), None).unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_test_error() {
+    let result = check(&fixture("app.toml"), None, ".upstreams[*].host", None, Some("10.0.0.11"), None).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 1);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.test, "structured_file - value");
+            assert_eq!(e.expected, ".upstreams[*].host=10.0.0.11");
+            assert_eq!(e.actual, format!(".upstreams[*].host=10.0.0.11, 10.0.0.12 ({})", fixture("app.toml")));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check(&fixture("app.yaml"), None, ".server.tls", None, Some("true"), None).unwrap();
+    assert_eq!(result.error, 1);
+    assert_eq!(result.summary.len(), 1);
+    let result = check(&fixture("app.json"), None, ".debug", Some("false"), None, Some("^true$")).unwrap();
+    assert_eq!(result.error, 2);
+    assert!(check(&fixture("invalid.json"), None, ".name", None, None, None).is_err());
+    assert!(check(&fixture("invalid.toml"), None, ".name", None, None, None).is_err());
+    assert!(check(&fixture("app.json"), None, ".server..port", None, None, None).is_err());
+    assert!(check(&fixture("app.json"), None, ".name", None, None, Some("(")).is_err());
+    assert!(check(&fixture("notexists.yaml"), None, ".name", None, None, None).is_err());
+}
@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::str;
+use regex::Regex;
+use modules::test;
+use modules::user;
+use modules::group;
+use util;
+
+const SUDOERS: &'static str = "/etc/sudoers";
+/// same limit as sudo
+const MAX_INCLUDE_DEPTH: usize = 128;
+const TAGS: [&'static str; 16] = ["NOPASSWD", "PASSWD", "NOEXEC", "EXEC", "SETENV", "NOSETENV", "LOG_INPUT",
+                                  "NOLOG_INPUT", "LOG_OUTPUT", "NOLOG_OUTPUT", "MAIL", "NOMAIL", "FOLLOW",
+                                  "NOFOLLOW", "INTERCEPT", "NOINTERCEPT"];
+
+#[derive(Debug)]
+pub enum SudoersError {
+    Io(io::Error),
+    ParseBool(str::ParseBoolError),
+    User(user::UserError),
+    Group(group::GroupError),
+    IncludeDepth(String),
+    Syntax(String)
+}
+
+impl From<io::Error> for SudoersError {
+    fn from(err: io::Error) -> SudoersError {
+        SudoersError::Io(err)
+    }
+}
+
+impl From<str::ParseBoolError> for SudoersError {
+    fn from(err: str::ParseBoolError) -> SudoersError {
+        SudoersError::ParseBool(err)
+    }
+}
+
+impl From<user::UserError> for SudoersError {
+    fn from(err: user::UserError) -> SudoersError {
+        SudoersError::User(err)
+    }
+}
+
+impl From<group::GroupError> for SudoersError {
+    fn from(err: group::GroupError) -> SudoersError {
+        SudoersError::Group(err)
+    }
+}
+
+/// one command of a user specification, runas and tags already carried over
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub users: Vec<String>,
+    pub runas: Vec<String>,
+    pub nopasswd: bool,
+    pub command: String,
+    pub negated: bool,
+    pub file: String,
+    pub line: usize
+}
+
+impl Rule {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+#[derive(Debug)]
+pub struct Sudoers {
+    /// User_Alias, Runas_Alias and Cmnd_Alias by name (Host_Alias is ignored)
+    pub aliases: HashMap<String, Vec<String>>,
+    pub rules: Vec<Rule>
+}
+
+/// the regexes of the parser, compiled once for all the statements
+struct Patterns {
+    /// `alice, bob` in the users of a user specification
+    user_separator: Regex,
+    /// `: host =` between the sections of a user specification
+    host_separator: Regex
+}
+
+impl Patterns {
+    fn new() -> Patterns {
+        Patterns {
+            user_separator: Regex::new(r",\s+").unwrap(),
+            host_separator: Regex::new(r"\s:\s*[^\s=()]+\s*=").unwrap()
+        }
+    }
+}
+
+/// splits on a separator outside of parentheses, `\` escapes the separator
+fn split_top_level(value: &str, separator: char) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => {
+                escaped = true;
+                current.push(c);
+            }
+            '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            c if c == separator && depth == 0 => {
+                items.push(current.trim().to_string());
+                current.clear();
+            }
+            c => current.push(c)
+        }
+    }
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+    items
+}
+
+/// a `#` starts a comment, except for `#include`, `#includedir` and uids (`#1000`)
+fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if b != b'#' || (i > 0 && !(bytes[i - 1] as char).is_whitespace()) {
+            continue;
+        }
+        let rest = &line[i..];
+        let is_uid = bytes.get(i + 1).map_or(false, |c| c.is_ascii_digit());
+        if !is_uid && !(i == 0 && rest.starts_with("#include")) {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+/// files of an includedir, sudo skips names containing a `.` or ending with `~`
+fn get_includedir_files(dir: &str) -> Result<Vec<String>, io::Error> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return Ok(Vec::new())
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let name = try!(entry).file_name().to_string_lossy().into_owned();
+        if !name.contains('.') && !name.ends_with('~') {
+            files.push(format!("{}/{}", dir, name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn parse_alias(definition: &str, sudoers: &mut Sudoers) {
+    for alias in split_top_level(definition, ':') {
+        if let Some(i) = alias.find('=') {
+            let members = split_top_level(&alias[i + 1..], ',');
+            sudoers.aliases.insert(alias[..i].trim().to_string(), members);
+        }
+    }
+}
+
+/// `(root)`, `(ALL:ALL)` or `(:wheel)`, only the runas users are kept
+fn parse_runas(runas: &str) -> Vec<String> {
+    let users = runas.trim_matches(|c| c == '(' || c == ')').split(':').next().unwrap_or("");
+    split_top_level(users, ',')
+}
+
+/// `users hosts = cmnd_spec_list [: hosts = cmnd_spec_list]`, hosts are not evaluated
+fn parse_user_spec(spec: &str,
+                   file: &str,
+                   line: usize,
+                   patterns: &Patterns,
+                   sudoers: &mut Sudoers) -> Result<(), SudoersError> {
+    let syntax_error = || SudoersError::Syntax(format!("{}:{}: {}", file, line, spec));
+    let equal = try!(spec.find('=').ok_or_else(&syntax_error));
+    let head = patterns.user_separator.replace_all(&spec[..equal], ",");
+    let users = match head.split_whitespace().next() {
+        Some(u) => split_top_level(u, ','),
+        None => return Err(syntax_error())
+    };
+    for section in patterns.host_separator.split(&spec[equal + 1..]) {
+        let mut runas = vec!["root".to_string()];
+        let mut nopasswd = false;
+        for item in split_top_level(section, ',') {
+            let mut command = item.as_str();
+            if command.starts_with('(') {
+                let end = try!(command.find(')').ok_or_else(&syntax_error));
+                runas = parse_runas(&command[..end + 1]);
+                command = command[end + 1..].trim_start();
+            }
+            loop {
+                let tag = match command.find(':') {
+                    Some(i) if TAGS.contains(&command[..i].trim()) => i,
+                    _ => break
+                };
+                match command[..tag].trim() {
+                    "NOPASSWD" => nopasswd = true,
+                    "PASSWD" => nopasswd = false,
+                    _ => {}
+                }
+                command = command[tag + 1..].trim_start();
+            }
+            let negated = command.starts_with('!');
+            sudoers.rules.push(Rule {
+                users: users.clone(),
+                runas: runas.clone(),
+                nopasswd: nopasswd,
+                command: command.trim_start_matches('!').trim().to_string(),
+                negated: negated,
+                file: file.to_string(),
+                line: line
+            });
+        }
+    }
+    Ok(())
+}
+
+fn parse_file(path: &str, depth: usize, patterns: &Patterns, sudoers: &mut Sudoers) -> Result<(), SudoersError> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(SudoersError::IncludeDepth(path.to_string()));
+    }
+    let dir = Path::new(path).parent().map_or(".".to_string(), |p| p.to_string_lossy().into_owned());
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut statement = String::new();
+    let mut statement_line = 0;
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        if statement.is_empty() {
+            statement_line = i + 1;
+        }
+        let line = strip_comment(&line).trim_end();
+        if line.ends_with('\\') {
+            statement.push_str(&line[..line.len() - 1]);
+            statement.push(' ');
+            continue;
+        }
+        statement.push_str(line);
+        let current = statement.trim().to_string();
+        statement.clear();
+        if current.is_empty() {
+            continue;
+        }
+        let mut words = current.splitn(2, char::is_whitespace);
+        let keyword = words.next().unwrap_or("");
+        let rest = words.next().unwrap_or("").trim();
+        let include_path = |p: &str| if p.starts_with('/') { p.to_string() } else { format!("{}/{}", dir, p) };
+        match keyword {
+            "#include" | "@include" => try!(parse_file(&include_path(rest), depth + 1, patterns, sudoers)),
+            "#includedir" | "@includedir" => {
+                for included in try!(get_includedir_files(&include_path(rest))) {
+                    try!(parse_file(&included, depth + 1, patterns, sudoers));
+                }
+            }
+            "User_Alias" | "Runas_Alias" | "Cmnd_Alias" | "Cmd_Alias" => parse_alias(rest, sudoers),
+            "Host_Alias" => {}
+            k if k.starts_with("Defaults") => {}
+            _ => try!(parse_user_spec(&current, path, statement_line, patterns, sudoers))
+        }
+    }
+    Ok(())
+}
+
+/// returns the aliases and rules of the file and of its includes, in order
+pub fn get_sudoers(path: &str) -> Result<Sudoers, SudoersError> {
+    let mut sudoers = Sudoers {
+        aliases: HashMap::new(),
+        rules: Vec::new()
+    };
+    try!(parse_file(path, 0, &Patterns::new(), &mut sudoers));
+    Ok(sudoers)
+}
+
+/// like sudo, the last matching item of a list wins, `!` negates an item. Aliases are expanded.
+fn list_matches<F: Fn(&str) -> bool>(items: &[String], aliases: &HashMap<String, Vec<String>>, item_matches: &F) -> Option<bool> {
+    list_matches_alias(items, aliases, item_matches, &mut Vec::new())
+}
+
+/// `visiting` are the aliases being expanded, an alias referencing itself doesn't match
+fn list_matches_alias<F: Fn(&str) -> bool>(items: &[String],
+                                           aliases: &HashMap<String, Vec<String>>,
+                                           item_matches: &F,
+                                           visiting: &mut Vec<String>) -> Option<bool> {
+    let mut matched = None;
+    for item in items {
+        let negated = item.starts_with('!');
+        let name = item.trim_start_matches('!').trim();
+        let item_match = match aliases.get(name) {
+            Some(_) if visiting.iter().any(|v| v == name) => false,
+            Some(members) => {
+                visiting.push(name.to_string());
+                let alias_match = list_matches_alias(members, aliases, item_matches, visiting) == Some(true);
+                visiting.pop();
+                alias_match
+            }
+            None => name == "ALL" || item_matches(name)
+        };
+        if item_match {
+            matched = Some(!negated);
+        }
+    }
+    matched
+}
+
+/// a command without arguments allows any arguments, `""` allows none, a directory allows its commands
+pub fn command_matches(pattern: &str, command: &str) -> bool {
+    let (pattern_path, pattern_args) = match pattern.find(char::is_whitespace) {
+        Some(i) => (&pattern[..i], Some(pattern[i..].trim())),
+        None => (pattern, None)
+    };
+    let (path, args) = match command.find(char::is_whitespace) {
+        Some(i) => (&command[..i], command[i..].trim()),
+        None => (command, "")
+    };
+    let path_matches = if pattern_path.ends_with('/') {
+        path.starts_with(pattern_path) && !path[pattern_path.len()..].contains('/')
+    }
+    else {
+        util::wildcard_match(pattern_path.as_bytes(), path.as_bytes())
+    };
+    path_matches && match pattern_args {
+        None => true,
+        Some("\"\"") => args.is_empty(),
+        Some(a) => util::wildcard_match(a.as_bytes(), args.as_bytes())
+    }
+}
+
+/// returns the rule deciding if the user may run the command, None when no rule matches
+pub fn get_deciding_rule<'a>(sudoers: &'a Sudoers,
+                             username: &str,
+                             groups: &[String],
+                             runas: &str,
+                             command: &str) -> Option<&'a Rule> {
+    let user_matches = |name: &str| {
+        if name.starts_with('%') {
+            groups.iter().any(|g| g == &name[1..])
+        }
+        else {
+            name == username
+        }
+    };
+    let runas_matches = |name: &str| name == runas;
+    let command_matches = |pattern: &str| command_matches(pattern, command);
+    sudoers.rules.iter()
+        .filter(|r| list_matches(&r.users, &sudoers.aliases, &user_matches) == Some(true))
+        .filter(|r| list_matches(&r.runas, &sudoers.aliases, &runas_matches) == Some(true))
+        .filter(|r| {
+            let command = vec![r.command.clone()];
+            list_matches(&command, &sudoers.aliases, &command_matches) == Some(true)
+        })
+        .last()
+}
+
+/// the principals of a list, aliases expanded: a negated item removes the principals added
+/// before it, like the last match wins. An alias referencing itself adds nothing.
+fn expand(items: &[String], aliases: &HashMap<String, Vec<String>>, visiting: &mut Vec<String>) -> Vec<String> {
+    let mut principals: Vec<String> = Vec::new();
+    for item in items {
+        let negated = item.starts_with('!');
+        let name = item.trim_start_matches('!').trim();
+        let names = match aliases.get(name) {
+            Some(_) if visiting.iter().any(|v| v == name) => Vec::new(),
+            Some(members) => {
+                visiting.push(name.to_string());
+                let names = expand(members, aliases, visiting);
+                visiting.pop();
+                names
+            }
+            None => vec![name.to_string()]
+        };
+        for name in names {
+            principals.retain(|p| p != &name);
+            if !negated {
+                principals.push(name);
+            }
+        }
+    }
+    principals
+}
+
+/// users and groups a rule applies to, without the excluded ones
+fn get_principals(rule: &Rule, aliases: &HashMap<String, Vec<String>>) -> Vec<String> {
+    expand(&rule.users, aliases, &mut Vec::new())
+}
+
+/// the groups of a user (primary and secondary) from the group module
+pub fn get_user_groups(username: &str) -> Result<Vec<String>, SudoersError> {
+    let mut groups = try!(group::get_user_secondary_groups(username));
+    if let Some(user) = try!(user::get_user(username)) {
+        groups.push(user.group);
+    }
+    Ok(groups)
+}
+
+fn check_can_run(username: &str,
+                 groups: &[String],
+                 runas: &str,
+                 command: &str,
+                 can_run: bool,
+                 sudoers: &Sudoers,
+                 result: &mut test::TestResult) {
+    let test_name = "sudoers - can run";
+    let rule = get_deciding_rule(sudoers, username, groups, runas, command);
+    let allowed = rule.map_or(false, |r| !r.negated);
+    let actual = match rule {
+        Some(r) => format!("{} ({})", allowed, r.location()),
+        None => "false (no matching rule)".to_string()
+    };
+    test::update_test_result(allowed == can_run, test_name,
+                             &format!("{} as {} can run {}: {}", username, runas, command, can_run),
+                             &actual, "incorrect sudo permission", result);
+}
+
+/// one error per rule granting sudo to a principal outside of the allow-list
+fn check_principals(test_name: &str,
+                    allowed: &str,
+                    nopasswd_only: bool,
+                    sudoers: &Sudoers,
+                    result: &mut test::TestResult) {
+    let allowed_list: Vec<&str> = allowed.split(',').filter(|a| !a.is_empty()).collect();
+    let mut reported: Vec<String> = Vec::new();
+    for rule in sudoers.rules.iter().filter(|r| !r.negated && (r.nopasswd || !nopasswd_only)) {
+        let denied: Vec<String> = get_principals(rule, &sudoers.aliases).into_iter()
+            .filter(|p| !allowed_list.contains(&p.as_str()))
+            .collect();
+        if !denied.is_empty() && !reported.contains(&rule.location()) {
+            reported.push(rule.location());
+            test::update_test_result(false, test_name, allowed, &format!("{} ({})", denied.join(","), rule.location()),
+                                     "principal not allowed", result);
+        }
+    }
+    if reported.is_empty() {
+        test::update_test_result(true, test_name, allowed, "", "", result);
+    }
+}
+
+pub fn check_sudoers(path: &str,
+                     username: Option<&str>,
+                     groups: &[String],
+                     command: Option<&str>,
+                     runas: Option<&str>,
+                     can_run: Option<&str>,
+                     allowed_users: Option<&str>,
+                     nopasswd_users: Option<&str>) -> Result<test::TestResult, SudoersError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let can_run = try!(util::parse_bool(can_run));
+    let sudoers = try!(get_sudoers(path));
+    if let (Some(username), Some(command)) = (username, command) {
+        let can_run = can_run.unwrap_or(true);
+        check_can_run(username, groups, runas.unwrap_or("root"), command, can_run, &sudoers, &mut result);
+    }
+    allowed_users.map(|a| {
+        check_principals("sudoers - users", a, false, &sudoers, &mut result);
+    });
+    nopasswd_users.map(|n| {
+        check_principals("sudoers - nopasswd", n, true, &sudoers, &mut result);
+    });
+    Ok(result)
+}
+
+pub fn check(username: Option<&str>,
+             command: Option<&str>,
+             runas: Option<&str>,
+             can_run: Option<&str>,
+             allowed_users: Option<&str>,
+             nopasswd_users: Option<&str>) -> Result<test::TestResult, SudoersError> {
+    let groups = match username {
+        Some(u) => try!(get_user_groups(u)),
+        None => Vec::new()
+    };
+    check_sudoers(SUDOERS, username, &groups, command, runas, can_run, allowed_users, nopasswd_users)
+}
+
+// TESTS
+
+#[cfg(test)]
+const SUDOERS_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/sudoers/sudoers");
+#[cfg(test)]
+const ALIASES_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/sudoers/aliases");
+
+#[test]
+fn split_top_level_test_success() {
+    assert_eq!(split_top_level("(root, admin) /bin/ls, /bin/cat", ','), vec!["(root, admin) /bin/ls", "/bin/cat"]);
+    assert_eq!(split_top_level("/bin/echo a\\,b, /bin/ls,", ','), vec!["/bin/echo a\\,b", "/bin/ls"]);
+    assert!(split_top_level("", ',').is_empty());
+}
+
+#[test]
+fn strip_comment_test_success() {
+    assert_eq!(strip_comment("root ALL=(ALL) ALL # comment"), "root ALL=(ALL) ALL ");
+    assert_eq!(strip_comment("#includedir /etc/sudoers.d"), "#includedir /etc/sudoers.d");
+    assert_eq!(strip_comment("#1000 ALL=(ALL) ALL"), "#1000 ALL=(ALL) ALL");
+    assert_eq!(strip_comment("# User privilege specification"), "");
+}
+
+#[test]
+fn command_matches_test_success() {
+    assert!(command_matches("/usr/bin/systemctl", "/usr/bin/systemctl restart nginx"));
+    assert!(command_matches("/usr/bin/systemctl restart *", "/usr/bin/systemctl restart nginx"));
+    assert!(!command_matches("/usr/bin/systemctl restart *", "/usr/bin/systemctl stop nginx"));
+    assert!(command_matches("/usr/bin/psql \"\"", "/usr/bin/psql"));
+    assert!(!command_matches("/usr/bin/psql \"\"", "/usr/bin/psql -c drop"));
+    assert!(command_matches("/usr/local/bin/", "/usr/local/bin/deploy --now"));
+    assert!(!command_matches("/usr/local/bin/", "/usr/local/bin/sub/deploy"));
+}
+
+#[test]
+fn get_sudoers_test_success() {
+    let sudoers = get_sudoers(SUDOERS_FIXTURE).unwrap();
+    assert_eq!(sudoers.aliases.get("ADMINS").unwrap(), &vec!["alice", "bob", "carol"]);
+    assert_eq!(sudoers.aliases.get("SERVICES").unwrap(),
+               &vec!["/usr/bin/systemctl restart *", "/usr/bin/systemctl status *"]);
+    assert_eq!(sudoers.rules.len(), 9);
+    assert!(sudoers.rules.iter().all(|r| r.users != vec!["%legacy"] && r.users != vec!["eve"]));
+    let rule = &sudoers.rules[3];
+    assert_eq!(rule.command, "SHELLS");
+    assert!(rule.negated);
+    assert_eq!(rule.line, 21);
+    let rule = &sudoers.rules[6];
+    assert_eq!(rule.users, vec!["%ops"]);
+    assert_eq!(rule.runas, vec!["ALL"]);
+    assert!(!rule.nopasswd);
+    let rule = &sudoers.rules[8];
+    assert_eq!(rule.runas, vec!["postgres"]);
+    assert_eq!(rule.command, "/usr/bin/psql \"\"");
+    assert!(!rule.nopasswd);
+}
+
+#[test]
+fn get_deciding_rule_test_success() {
+    let sudoers = get_sudoers(SUDOERS_FIXTURE).unwrap();
+    let no_groups: Vec<String> = Vec::new();
+    let rule = get_deciding_rule(&sudoers, "alice", &no_groups, "root", "/usr/bin/apt update").unwrap();
+    assert!(!rule.negated);
+    assert!(get_deciding_rule(&sudoers, "alice", &no_groups, "root", "/bin/bash").unwrap().negated);
+    assert!(get_deciding_rule(&sudoers, "alice", &no_groups, "postgres", "/usr/bin/apt").is_none());
+    let interns = vec!["interns".to_string()];
+    let rule = get_deciding_rule(&sudoers, "dave", &interns, "www-data", "/usr/bin/tail /var/log/nginx/error.log");
+    assert!(rule.unwrap().nopasswd);
+    assert!(get_deciding_rule(&sudoers, "carol", &interns, "www-data", "/usr/bin/tail /var/log/nginx/error.log").is_none());
+    let ops = vec!["ops".to_string()];
+    // the last match wins, PASSWD: ALL comes after NOPASSWD: SERVICES
+    let rule = get_deciding_rule(&sudoers, "frank", &ops, "root", "/usr/bin/systemctl restart nginx").unwrap();
+    assert_eq!(rule.command, "ALL");
+    assert!(!rule.nopasswd);
+}
+
+#[test]
+fn get_principals_test_aliases() {
+    let sudoers = get_sudoers(ALIASES_FIXTURE).unwrap();
+    assert_eq!(sudoers.rules.len(), 4);
+    assert_eq!(get_principals(&sudoers.rules[0], &sudoers.aliases), vec!["alice", "bob"]);
+    assert_eq!(get_principals(&sudoers.rules[1], &sudoers.aliases), vec!["carol"]);
+    assert_eq!(get_principals(&sudoers.rules[2], &sudoers.aliases), vec!["alice", "dave"]);
+    assert_eq!(get_principals(&sudoers.rules[3], &sudoers.aliases), vec!["alice", "bob"]);
+    let no_groups: Vec<String> = Vec::new();
+    assert!(get_deciding_rule(&sudoers, "bob", &no_groups, "root", "/usr/bin/uptime").is_some());
+    assert!(get_deciding_rule(&sudoers, "carol", &no_groups, "root", "/usr/bin/id").is_some());
+    assert!(get_deciding_rule(&sudoers, "bob", &no_groups, "root", "/usr/sbin/reboot").is_none());
+    assert!(get_deciding_rule(&sudoers, "dave", &no_groups, "root", "/usr/bin/apt").is_none());
+    let result = check_sudoers(ALIASES_FIXTURE, None, &no_groups, None, None, None, None, Some("alice,dave")).unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 0);
+}
+
+#[test]
+fn check_sudoers_test_success() {
+    let groups = vec!["deploy".to_string()];
+    let result = check_sudoers(SUDOERS_FIXTURE, Some("deploy"), &groups, Some("/usr/bin/psql"), Some("postgres"),
+                               Some("true"), Some("root,%sudo,ADMINS,alice,bob,carol,%interns,%ops,deploy"),
+                               Some("%ops,%interns,deploy")).unwrap();
+    assert_eq!(result.success, 3);
+    assert_eq!(result.error, 0);
+    let result = check_sudoers(SUDOERS_FIXTURE, Some("deploy"), &groups, Some("/usr/bin/psql -c vacuum"),
+                               Some("postgres"), Some("false"), None, None).unwrap();
+    assert_eq!(result.success, 1);
+}
+
+#[test]
+fn check_sudoers_test_error() {
+    let groups = vec!["users".to_string()];
+    let result = check_sudoers(SUDOERS_FIXTURE, Some("alice"), &groups, Some("/bin/sh"), None, Some("true"),
+                               Some("root,%sudo,%ops"), Some("%ops")).unwrap();
+    assert_eq!(result.error, 6);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "alice as root can run /bin/sh: true");
+            assert_eq!(e.actual, format!("false ({}:21)", SUDOERS_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, format!("alice,bob,carol ({}:21)", SUDOERS_FIXTURE)),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[4] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, format!("%interns ({}:22)", SUDOERS_FIXTURE)),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[5] {
+        test::UnitResult::Error(ref e) => assert!(e.actual.starts_with("deploy (")),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert!(check_sudoers("/notexists", None, &groups, None, None, None, None, None).is_err());
+}
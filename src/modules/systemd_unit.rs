@@ -0,0 +1,330 @@
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use modules::service;
+use modules::test;
+use util;
+
+/// directives accumulating their values, an empty assignment resets the list
+const LIST_DIRECTIVES: [&'static str; 24] = ["After", "Before", "Wants", "Requires", "Requisite", "BindsTo", "PartOf",
+                                              "Conflicts", "WantedBy", "RequiredBy", "Also", "Alias", "ExecStart",
+                                              "ExecStartPre", "ExecStartPost", "ExecStop", "ExecStopPost", "ExecReload",
+                                              "Environment", "EnvironmentFile", "ReadWritePaths", "ReadOnlyPaths",
+                                              "InaccessiblePaths", "OnCalendar"];
+
+#[derive(Debug)]
+pub enum SystemdUnitError {
+    Io(io::Error),
+    Directive(String)
+}
+
+impl From<io::Error> for SystemdUnitError {
+    fn from(err: io::Error) -> SystemdUnitError {
+        SystemdUnitError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Directive {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+    pub file: String,
+    pub line: usize
+}
+
+impl Directive {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+}
+
+/// the unit file with the highest precedence, the template for instances (`getty@tty1.service`
+/// uses `getty@.service`). Err contains the path when the unit is masked.
+pub fn find_unit_file(root: &str, unit: &str) -> Result<Option<String>, String> {
+    let mut names = vec![unit.to_string()];
+    names.extend(get_template_name(unit));
+    for name in &names {
+        for dir in service::SYSTEMD_UNIT_DIRS.iter() {
+            let path = util::join_root(root, &format!("{}/{}", dir, name));
+            if let Ok(target) = fs::read_link(&path) {
+                if target == Path::new("/dev/null") {
+                    return Err(path);
+                }
+            }
+            if fs::metadata(&path).map(|m| m.is_file()).unwrap_or(false) {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn get_template_name(unit: &str) -> Option<String> {
+    let at = match unit.find('@') {
+        Some(i) => i,
+        None => return None
+    };
+    let dot = match unit.rfind('.') {
+        Some(i) if i > at + 1 => i,
+        _ => return None
+    };
+    Some(format!("{}{}", &unit[..at + 1], &unit[dot..]))
+}
+
+/// `*.conf` of `<unit>.d`, `<template>.d` and `<type>.d` (like `service.d`). For files with the same
+/// name, the one of the directory with the highest precedence wins. Drop-ins are sorted by file name.
+pub fn get_drop_ins(root: &str, unit: &str) -> Result<Vec<String>, io::Error> {
+    let mut names = vec![unit.to_string()];
+    names.extend(get_template_name(unit));
+    if let Some(i) = unit.rfind('.') {
+        names.push(unit[i + 1..].to_string());
+    }
+    let mut drop_ins: Vec<(String, String)> = Vec::new();
+    for dir in service::SYSTEMD_UNIT_DIRS.iter() {
+        for name in &names {
+            let drop_in_dir = util::join_root(root, &format!("{}/{}.d", dir, name));
+            let entries = match fs::read_dir(&drop_in_dir) {
+                Ok(e) => e,
+                Err(_) => continue
+            };
+            for entry in entries {
+                let file_name = try!(entry).file_name().to_string_lossy().into_owned();
+                if file_name.ends_with(".conf") && !drop_ins.iter().any(|d| d.0 == file_name) {
+                    let path = format!("{}/{}", drop_in_dir, file_name);
+                    drop_ins.push((file_name, path));
+                }
+            }
+        }
+    }
+    drop_ins.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(drop_ins.into_iter().map(|d| d.1).collect())
+}
+
+/// reads the directives of a unit file or a drop-in, a trailing backslash continues the line
+pub fn parse_file(path: &str, directives: &mut Vec<Directive>) -> Result<(), io::Error> {
+    let file = try!(fs::File::open(path));
+    let reader = io::BufReader::new(file);
+    let mut section = "".to_string();
+    let mut pending: Option<(String, usize)> = None;
+    for (i, l) in reader.lines().enumerate() {
+        let line = try!(l);
+        let (line, start) = match pending.take() {
+            Some((previous, start)) => (format!("{} {}", previous, line.trim()), start),
+            None => (line.trim().to_string(), i + 1)
+        };
+        if line.ends_with('\\') {
+            pending = Some((line.trim_end_matches('\\').trim().to_string(), start));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+        if let Some(i) = line.find('=') {
+            directives.push(Directive {
+                section: section.clone(),
+                key: line[..i].trim().to_string(),
+                value: line[i + 1..].trim().to_string(),
+                file: path.to_string(),
+                line: start
+            });
+        }
+    }
+    Ok(())
+}
+
+/// values of a directive once the unit file and its drop-ins are merged, in any section
+/// when `section` is None. Values before an empty assignment are dropped.
+pub fn get_values<'a>(directives: &'a Vec<Directive>, section: Option<&str>, key: &str) -> Vec<&'a Directive> {
+    let mut values = Vec::new();
+    for directive in directives {
+        if directive.key != key || section.map_or(false, |s| directive.section != s) {
+            continue;
+        }
+        if directive.value.is_empty() {
+            values.clear();
+        }
+        else {
+            values.push(directive);
+        }
+    }
+    values
+}
+
+/// `Service.User=app` or `User=app`
+fn split_directive(directive: &str) -> Result<(Option<&str>, &str, &str), SystemdUnitError> {
+    let (name, value) = match directive.find('=') {
+        Some(i) if i > 0 => (directive[..i].trim(), directive[i + 1..].trim()),
+        _ => return Err(SystemdUnitError::Directive(directive.to_string()))
+    };
+    match name.rfind('.') {
+        Some(i) => Ok((Some(&name[..i]), &name[i + 1..], value)),
+        None => Ok((None, name, value))
+    }
+}
+
+/// the last value wins, except for the directives of LIST_DIRECTIVES where one of the values
+/// must match. An empty expected value means the directive is not set.
+fn check_directive(key: &str, value: &str, values: &Vec<&Directive>, result: &mut test::TestResult) {
+    let test_name = "systemd_unit - directive";
+    let effective: Vec<&Directive> = if LIST_DIRECTIVES.contains(&key) {
+        values.clone()
+    }
+    else {
+        values.last().into_iter().cloned().collect()
+    };
+    let condition = if value.is_empty() {
+        effective.is_empty()
+    }
+    else {
+        effective.iter().any(|d| d.value == value)
+    };
+    let actual = if effective.is_empty() {
+        format!("{} not set", key)
+    }
+    else {
+        effective.iter().map(|d| format!("{}={} ({})", key, d.value, d.location())).collect::<Vec<String>>().join(",")
+    };
+    test::update_test_result(condition, test_name, &format!("{}={}", key, value), &actual, "incorrect directive",
+                             result);
+}
+
+pub fn check_systemd_unit(root: &str, name: &str, directives: &[&str]) -> Result<test::TestResult, SystemdUnitError> {
+    let mut result = test::TestResult {
+        success: 0,
+        error: 0,
+        summary: Vec::new()
+    };
+    let test_name = "systemd_unit - file";
+    let unit = service::get_unit_name(name);
+    let path = match find_unit_file(root, &unit) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            test::update_test_result(false, test_name, &unit, "not found", "unit file not found", &mut result);
+            return Ok(result);
+        }
+        Err(path) => {
+            test::update_test_result(false, test_name, &unit, &format!("masked by {}", path), "unit is masked",
+                                     &mut result);
+            return Ok(result);
+        }
+    };
+    let drop_ins = try!(get_drop_ins(root, &unit));
+    let mut parsed = Vec::new();
+    try!(parse_file(&path, &mut parsed));
+    for drop_in in &drop_ins {
+        try!(parse_file(drop_in, &mut parsed));
+    }
+    let mut files = vec![path];
+    files.extend(drop_ins);
+    test::update_test_result(true, test_name, &unit, &files.join(","), "", &mut result);
+    for directive in directives {
+        let (section, key, value) = try!(split_directive(directive));
+        check_directive(key, value, &get_values(&parsed, section, key), &mut result);
+    }
+    Ok(result)
+}
+
+pub fn check(name: &str, directives: &[&str], root: Option<&str>) -> Result<test::TestResult, SystemdUnitError> {
+    check_systemd_unit(root.unwrap_or("/"), name, directives)
+}
+
+// TESTS
+
+#[cfg(test)]
+const ROOT_FIXTURE: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/fixtures/systemd_unit");
+
+#[test]
+fn find_unit_file_test_success() {
+    assert_eq!(find_unit_file(ROOT_FIXTURE, "app.service"),
+               Ok(Some(format!("{}/usr/lib/systemd/system/app.service", ROOT_FIXTURE))));
+    assert_eq!(find_unit_file(ROOT_FIXTURE, "getty@tty1.service"),
+               Ok(Some(format!("{}/usr/lib/systemd/system/getty@.service", ROOT_FIXTURE))));
+    assert_eq!(find_unit_file(ROOT_FIXTURE, "telnet.service"),
+               Err(format!("{}/etc/systemd/system/telnet.service", ROOT_FIXTURE)));
+    assert_eq!(find_unit_file(ROOT_FIXTURE, "cron.service"), Ok(None));
+}
+
+#[test]
+fn get_drop_ins_test_success() {
+    let drop_ins = get_drop_ins(ROOT_FIXTURE, "app.service").unwrap();
+    assert_eq!(drop_ins, vec![format!("{}/usr/lib/systemd/system/service.d/05-timeout.conf", ROOT_FIXTURE),
+                              format!("{}/etc/systemd/system/app.service.d/10-user.conf", ROOT_FIXTURE),
+                              format!("{}/etc/systemd/system/app.service.d/20-hardening.conf", ROOT_FIXTURE),
+                              format!("{}/run/systemd/system/app.service.d/30-restart.conf", ROOT_FIXTURE)]);
+    let drop_ins = get_drop_ins(ROOT_FIXTURE, "getty@tty1.service").unwrap();
+    assert_eq!(drop_ins.len(), 2);
+    assert!(get_drop_ins(ROOT_FIXTURE, "backup.timer").unwrap()[0].ends_with("backup.timer.d/override.conf"));
+}
+
+#[test]
+fn parse_file_test_success() {
+    let mut directives = Vec::new();
+    parse_file(&format!("{}/run/systemd/system/app.service.d/30-restart.conf", ROOT_FIXTURE), &mut directives).unwrap();
+    assert_eq!(directives.len(), 3);
+    assert_eq!(directives[0].section, "Service");
+    assert_eq!(directives[1].value, "");
+    assert_eq!(directives[2].value, "/usr/bin/app --config /etc/app.conf");
+    assert_eq!(directives[2].line, 4);
+}
+
+#[test]
+fn get_values_test_success() {
+    let mut directives = Vec::new();
+    parse_file(&format!("{}/lib/systemd/system/backup.timer", ROOT_FIXTURE), &mut directives).unwrap();
+    parse_file(&format!("{}/etc/systemd/system/backup.timer.d/override.conf", ROOT_FIXTURE), &mut directives).unwrap();
+    let values = get_values(&directives, Some("Timer"), "OnCalendar");
+    assert_eq!(values.len(), 1);
+    assert_eq!(values[0].value, "daily");
+    assert_eq!(values[0].line, 3);
+    assert!(get_values(&directives, Some("Unit"), "OnCalendar").is_empty());
+    assert_eq!(get_values(&directives, None, "Persistent")[0].value, "true");
+}
+
+#[test]
+fn check_systemd_unit_test_success() {
+    let result = check_systemd_unit(ROOT_FIXTURE, "backup.timer", &["OnCalendar=daily"]).unwrap();
+    assert_eq!(result.success, 2);
+    assert_eq!(result.error, 0);
+    let result = check_systemd_unit(ROOT_FIXTURE, "app", &["Service.User=app", "Restart=always", "ProtectSystem=strict",
+                                                           "NoNewPrivileges=", "TimeoutStopSec=30",
+                                                           "Environment=APP_ENV=production",
+                                                           "ExecStart=/usr/bin/app --config /etc/app.conf",
+                                                           "Install.WantedBy=multi-user.target"]).unwrap();
+    assert_eq!(result.success, 9);
+    assert_eq!(result.error, 0);
+    let result = check_systemd_unit(ROOT_FIXTURE, "getty@tty1", &["TTYVTDisallocate=no"]).unwrap();
+    assert_eq!(result.success, 2);
+}
+
+#[test]
+fn check_systemd_unit_test_error() {
+    let result = check_systemd_unit(ROOT_FIXTURE, "app.service", &["User=nobody", "Unit.User=app", "ExecStart=/usr/bin/app"])
+        .unwrap();
+    assert_eq!(result.success, 1);
+    assert_eq!(result.error, 3);
+    match result.summary[1] {
+        test::UnitResult::Error(ref e) => {
+            assert_eq!(e.expected, "User=nobody");
+            assert_eq!(e.actual, format!("User=app ({}/etc/systemd/system/app.service.d/10-user.conf:2)", ROOT_FIXTURE));
+        }
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    match result.summary[2] {
+        test::UnitResult::Error(ref e) => assert_eq!(e.actual, "User not set"),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    let result = check_systemd_unit(ROOT_FIXTURE, "telnet", &["User=telnet"]).unwrap();
+    assert_eq!(result.error, 1);
+    match result.summary[0] {
+        test::UnitResult::Error(ref e) => assert!(e.actual.starts_with("masked by")),
+        test::UnitResult::Success(_) => panic!("error in test")
+    }
+    assert_eq!(check_systemd_unit(ROOT_FIXTURE, "cron", &[]).unwrap().error, 1);
+    assert!(check_systemd_unit(ROOT_FIXTURE, "app", &["User"]).is_err());
+}
@@ -168,7 +168,7 @@ pub fn check(username: &str,
 }
 
 /// for a given username, returns an Option<User>
-fn get_user(username: &str) -> Result<Option<User>, UserError> {
+pub fn get_user(username: &str) -> Result<Option<User>, UserError> {
     let user_line = try!(get_user_line(username));
     match user_line {
         None => Ok(None),
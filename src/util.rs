@@ -1,5 +1,7 @@
 use std::num;
 use std::str;
+use std::time::Duration;
+use rustc_serialize::json::Json;
 
 pub fn parse_int(value: Option<&str>) -> Result<Option<i32>, num::ParseIntError> {
     match value {
@@ -21,6 +23,87 @@ pub fn parse_bool(value: Option<&str>) -> Result<Option<bool>, str::ParseBoolErr
     }
 }
 
+#[derive(Debug)]
+pub enum DurationError {
+    ParseFloat(num::ParseFloatError),
+    /// negative, infinite or shorter than a millisecond
+    Invalid(String)
+}
+
+impl From<num::ParseFloatError> for DurationError {
+    fn from(err: num::ParseFloatError) -> DurationError {
+        DurationError::ParseFloat(err)
+    }
+}
+
+/// parses a number of seconds, fractions allowed (`0.5`), the duration must be at least a millisecond
+pub fn parse_duration(value: Option<&str>) -> Result<Option<Duration>, DurationError> {
+    match value {
+        None => Ok(None),
+        Some(v) => {
+            let millis = try!(v.parse::<f64>()) * 1000.0;
+            if !millis.is_finite() || millis < 1.0 {
+                return Err(DurationError::Invalid(v.to_string()));
+            }
+            Ok(Some(Duration::from_millis(millis as u64)))
+        }
+    }
+}
+
+pub fn format_duration(duration: &Duration) -> String {
+    format!("{}.{:03}s", duration.as_secs(), duration.subsec_nanos() / 1_000_000)
+}
+
+/// prefixes an absolute system path with a root directory (`/` for the running system)
+pub fn join_root(root: &str, path: &str) -> String {
+    format!("{}/{}", root.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// shell-like wildcard matching with `*` and `?`
+pub fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(&b'*'), _) => wildcard_match(&pattern[1..], name) || (!name.is_empty() && wildcard_match(pattern, &name[1..])),
+        (Some(&b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false
+    }
+}
+
+/// splits `KEY=VALUE`, `KEY>=VALUE` or `KEY<=VALUE` into the key, the operator and the value
+pub fn split_comparison(setting: &str) -> Option<(&str, &str, &str)> {
+    let i = match setting.find('=') {
+        Some(i) if i > 0 => i,
+        _ => return None
+    };
+    let (key, operator) = match setting[..i].chars().last() {
+        Some('>') => (&setting[..i - 1], ">="),
+        Some('<') => (&setting[..i - 1], "<="),
+        _ => (&setting[..i], "=")
+    };
+    if key.trim().is_empty() {
+        return None;
+    }
+    Some((key.trim(), operator, setting[i + 1..].trim()))
+}
+
+/// applies an operator of split_comparison
+pub fn compare<T: PartialOrd>(operator: &str, actual: T, expected: T) -> bool {
+    match operator {
+        ">=" => actual >= expected,
+        "<=" => actual <= expected,
+        _ => actual == expected
+    }
+}
+
+/// strings without their quotes, the JSON encoding of other values
+pub fn json_to_string(json: &Json) -> String {
+    match *json {
+        Json::String(ref s) => s.clone(),
+        ref other => other.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +143,54 @@ mod tests {
         let result = parse_bool(Some("1"));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn parse_duration_test_success() {
+        let result = parse_duration(Some("2")).unwrap();
+        assert_eq!(result, Some(Duration::from_secs(2)));
+        let result = parse_duration(Some("0.25")).unwrap();
+        assert_eq!(result, Some(Duration::from_millis(250)));
+        let result = parse_duration(None).unwrap();
+        assert!(result.is_none());
+        assert_eq!(format_duration(&Duration::from_millis(1250)), "1.250s");
+    }
+
+    #[test]
+    fn parse_duration_test_error() {
+        let result = parse_duration(Some("soon"));
+        assert!(result.is_err());
+        for value in &["0", "-1", "0.0001", "NaN", "inf"] {
+            assert!(parse_duration(Some(value)).is_err());
+        }
+    }
+
+    #[test]
+    fn wildcard_match_test_success() {
+        assert!(wildcard_match(b"*.conf", b"10-hardening.conf"));
+        assert!(wildcard_match(b"?0-*", b"50-cloud-init.conf"));
+        assert!(wildcard_match(b"*", b""));
+        assert!(!wildcard_match(b"*.conf", b"README"));
+        assert!(!wildcard_match(b"a?", b"a"));
+    }
+
+    #[test]
+    fn split_comparison_test_success() {
+        assert_eq!(split_comparison("nofile=1024"), Some(("nofile", "=", "1024")));
+        assert_eq!(split_comparison("nofile>=65536"), Some(("nofile", ">=", "65536")));
+        assert_eq!(split_comparison("PASS_MAX_DAYS <= 90"), Some(("PASS_MAX_DAYS", "<=", "90")));
+        assert_eq!(split_comparison("UMASK="), Some(("UMASK", "=", "")));
+        assert_eq!(split_comparison("nofile"), None);
+        assert_eq!(split_comparison(">=3"), None);
+        assert!(compare(">=", 65536, 1024));
+        assert!(!compare("<=", 99999, 90));
+        assert!(compare("=", "yes", "yes"));
+    }
+
+    #[test]
+    fn json_to_string_test_success() {
+        let json = Json::from_str("{\"a\":\"b\",\"c\":[1,true],\"d\":null}").unwrap();
+        assert_eq!(json_to_string(json.find("a").unwrap()), "b");
+        assert_eq!(json_to_string(json.find("c").unwrap()), "[1,true]");
+        assert_eq!(json_to_string(json.find("d").unwrap()), "null");
+    }
 }